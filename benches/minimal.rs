@@ -0,0 +1,45 @@
+//! 放入、滚动、弹出和取消的吞吐量，分别在默认特性和minimal特性下运行以对比计数和观察钩子的开销：
+//! `cargo bench --bench minimal` 与 `cargo bench --bench minimal --features minimal`
+
+use std::{hint::black_box, time::Instant};
+
+use pi_cancel_timer::Timer;
+
+const ROUNDS: usize = 200;
+const TASKS: usize = 10_000;
+
+fn main() {
+    let mut timer: Timer<u64, 256, 64, 2> = Default::default();
+    let mut keys = Vec::with_capacity(TASKS);
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    // 预热，节点进入slotmap的空闲链表
+    timer.warm_up(TASKS);
+    let start = Instant::now();
+    let mut popped = 0u64;
+    for _ in 0..ROUNDS {
+        for i in 0..TASKS {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            keys.push(timer.push((seed % 5000) as usize, i as u64));
+        }
+        // 取消一半
+        for key in keys.drain(..).step_by(2) {
+            black_box(timer.cancel(key));
+        }
+        let now = timer.now().get() + 5000;
+        while let Some(el) = timer.pop(now) {
+            popped += black_box(el) & 1;
+        }
+    }
+    let elapsed = start.elapsed();
+    let ops = (ROUNDS * TASKS * 2) as f64;
+    println!(
+        "minimal={} ops={} elapsed={:?} ns/op={:.2} checksum={}",
+        cfg!(feature = "minimal"),
+        ops,
+        elapsed,
+        elapsed.as_nanos() as f64 / ops,
+        popped
+    );
+}
@@ -0,0 +1,45 @@
+//! 溢出存储的开销，轮很小，几乎全部任务都在堆上，测放入、取消和级联弹出，
+//! 分别用 `--features overflow_dary4`、`overflow_dary8`、`overflow_pairing`、`overflow_btree` 运行对比
+
+use std::{hint::black_box, time::Instant};
+
+use pi_cancel_timer::Timer;
+
+fn run(tasks: usize) {
+    let mut timer: Timer<u64, 16, 16, 1> = Default::default();
+    let mut keys = Vec::with_capacity(tasks);
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    let rounds = (2_000_000 / tasks).max(1);
+    let start = Instant::now();
+    let mut checksum = 0u64;
+    for _ in 0..rounds {
+        for i in 0..tasks {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            keys.push(timer.push(256 + (seed % 100_000) as usize, i as u64));
+        }
+        // 取消四分之一
+        for key in keys.drain(..).step_by(4) {
+            black_box(timer.cancel(key));
+        }
+        let now = timer.now().get() + 100_256;
+        while let Some(el) = timer.pop(now) {
+            checksum = checksum.wrapping_add(black_box(el));
+        }
+    }
+    let elapsed = start.elapsed();
+    let ops = (rounds * tasks) as f64;
+    println!(
+        "tasks={:>7} ns/task={:>8.2} checksum={}",
+        tasks,
+        elapsed.as_nanos() as f64 / ops,
+        checksum
+    );
+}
+
+fn main() {
+    for tasks in [1_000, 10_000, 100_000, 1_000_000] {
+        run(tasks);
+    }
+}
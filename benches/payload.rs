@@ -0,0 +1,51 @@
+//! 不同定时元素的吞吐量，对比内联的小元素、装箱的元素和较大的内联元素：
+//! `cargo bench --bench payload`
+
+use std::{hint::black_box, time::Instant};
+
+use pi_cancel_timer::Timer;
+
+const ROUNDS: usize = 200;
+const TASKS: usize = 10_000;
+
+fn run<T: Default>(name: &str, make: impl Fn(u32) -> T, read: impl Fn(&T) -> u32) {
+    let mut timer: Timer<T, 256, 64, 2> = Default::default();
+    let mut keys = Vec::with_capacity(TASKS);
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    // 预热，节点进入slotmap的空闲链表
+    timer.warm_up(TASKS);
+    let start = Instant::now();
+    let mut popped = 0u64;
+    for _ in 0..ROUNDS {
+        for i in 0..TASKS {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            keys.push(timer.push((seed % 5000) as usize, make(i as u32)));
+        }
+        // 取消一半
+        for key in keys.drain(..).step_by(2) {
+            black_box(timer.cancel(key));
+        }
+        let now = timer.now().get() + 5000;
+        while let Some(el) = timer.pop(now) {
+            popped += (read(&black_box(el)) & 1) as u64;
+        }
+    }
+    let elapsed = start.elapsed();
+    let ops = (ROUNDS * TASKS * 2) as f64;
+    println!(
+        "payload={} size={} elapsed={:?} ns/op={:.2} checksum={}",
+        name,
+        std::mem::size_of::<T>(),
+        elapsed,
+        elapsed.as_nanos() as f64 / ops,
+        popped
+    );
+}
+
+fn main() {
+    run("u32", |i| i, |el| *el);
+    run("Box<u32>", Box::new, |el| **el);
+    run("[u32; 16]", |i| [i; 16], |el| el[0]);
+}
@@ -0,0 +1,17 @@
+//! 随机的放入、取消、滚动和弹出操作序列，与参考模型逐步比较
+//! 运行：cargo +nightly fuzz run ops
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pi_cancel_timer::testing::{check, Op};
+
+fuzz_target!(|ops: Vec<Op>| {
+    // 很小的轮，让任务频繁经过上层轮和堆的级联
+    if let Err(e) = check::<4, 4, 2>(&ops) {
+        panic!("{}", e);
+    }
+    if let Err(e) = check::<8, 2, 1>(&ops) {
+        panic!("{}", e);
+    }
+});
@@ -0,0 +1,105 @@
+//! 异构定时器，定时元素为类型擦除的 `Box<dyn Any + Send>`，多种元素类型共用一个定时器和一套关键字
+
+use std::any::Any;
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{tick::Tick, Timer};
+
+/// 类型擦除的定时元素
+pub type AnyBox = Box<dyn Any + Send>;
+
+/// 异构定时器
+pub struct AnyTimer<const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<AnyBox, N0, N, L>,
+}
+
+impl<const N0: usize, const N: usize, const L: usize> Default for AnyTimer<N0, N, L> {
+    fn default() -> Self {
+        AnyTimer {
+            timer: Default::default(),
+        }
+    }
+}
+
+impl<const N0: usize, const N: usize, const L: usize> AnyTimer<N0, N, L> {
+    /// 获得内部的定时器
+    pub fn timer(&self) -> &Timer<AnyBox, N0, N, L> {
+        &self.timer
+    }
+    /// 获得内部的定时器
+    pub fn timer_mut(&mut self) -> &mut Timer<AnyBox, N0, N, L> {
+        &mut self.timer
+    }
+    /// 放入一个指定类型的定时任务
+    pub fn push_typed<T: Any + Send>(&mut self, timeout: usize, el: T) -> TimerKey {
+        self.timer.push(timeout, Box::new(el))
+    }
+    /// 判断定时任务是否为指定类型
+    pub fn is<T: Any>(&self, key: TimerKey) -> bool {
+        self.get::<T>(key).is_some()
+    }
+    /// 获得指定类型的定时元素，任务不存在或类型不符时返回None
+    pub fn get<T: Any>(&self, key: TimerKey) -> Option<&T> {
+        self.timer
+            .slot
+            .get(key)
+            .and_then(|node| node.el.el.el.downcast_ref())
+    }
+    /// 取消定时任务，返回类型擦除的定时元素
+    pub fn cancel(&mut self, key: TimerKey) -> Option<AnyBox> {
+        self.timer.cancel(key)
+    }
+    /// 取消指定类型的定时任务，类型不符时不取消，返回None
+    pub fn cancel_typed<T: Any>(&mut self, key: TimerKey) -> Option<T> {
+        if !self.is::<T>(key) {
+            return None;
+        }
+        self.timer
+            .cancel(key)
+            .and_then(|el| el.downcast().ok())
+            .map(|el| *el)
+    }
+    /// 弹出指定时间内的一个关键字和类型擦除的定时元素
+    pub fn pop(&mut self, now: u64) -> Option<(TimerKey, AnyBox)> {
+        self.timer.pop_at(Tick(now))
+    }
+    /// 下一个到期任务为指定类型时弹出，否则不弹出，返回None
+    /// * @tip 类型不符的到期任务会挡住后面的任务，应改用 `pop` 取出
+    pub fn pop_downcast<T: Any>(&mut self, now: u64) -> Option<(TimerKey, T)> {
+        if !self.timer.peek_kv(now)?.1.is::<T>() {
+            return None;
+        }
+        let (key, el) = self.timer.pop_at(Tick(now))?;
+        el.downcast().ok().map(|el| (key, *el))
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.timer.len()
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.timer.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any() {
+        let mut timer: AnyTimer<4, 4, 1> = Default::default();
+        let a = timer.push_typed(1, 1u32);
+        let b = timer.push_typed(2, "two");
+        let c = timer.push_typed(3, 3u64);
+        assert_eq!(timer.get::<u32>(a), Some(&1));
+        assert!(!timer.is::<u32>(b));
+        assert_eq!(timer.cancel_typed::<u32>(c), None);
+        assert_eq!(timer.cancel_typed::<u64>(c), Some(3));
+        assert_eq!(timer.pop_downcast::<&str>(2), None);
+        assert_eq!(timer.pop_downcast::<u32>(2), Some((a, 1)));
+        assert_eq!(timer.pop_downcast::<&str>(2), Some((b, "two")));
+        assert!(timer.is_empty());
+    }
+}
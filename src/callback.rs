@@ -0,0 +1,70 @@
+//! 回调定时器，定时任务为回调函数，到期时直接调用
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::Timer;
+
+/// 回调函数
+pub type Callback = Box<dyn FnOnce() + Send + 'static>;
+
+/// 回调定时器
+pub struct CallbackTimer<const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<Callback, N0, N, L>,
+    /// 是否捕获回调的panic
+    catch_unwind: bool,
+    /// 回调panic的次数
+    panic_count: usize,
+}
+
+impl<const N0: usize, const N: usize, const L: usize> Default for CallbackTimer<N0, N, L> {
+    fn default() -> Self {
+        CallbackTimer {
+            timer: Default::default(),
+            catch_unwind: false,
+            panic_count: 0,
+        }
+    }
+}
+
+impl<const N0: usize, const N: usize, const L: usize> CallbackTimer<N0, N, L> {
+    /// 获得内部的定时器
+    pub fn timer(&self) -> &Timer<Callback, N0, N, L> {
+        &self.timer
+    }
+    /// 获得内部的定时器
+    pub fn timer_mut(&mut self) -> &mut Timer<Callback, N0, N, L> {
+        &mut self.timer
+    }
+    /// 设置是否捕获回调的panic，捕获后一个回调的panic不会影响后续回调的执行
+    pub fn set_catch_unwind(&mut self, catch: bool) {
+        self.catch_unwind = catch;
+    }
+    /// 获得捕获到的回调panic的次数
+    pub fn panic_count(&self) -> usize {
+        self.panic_count
+    }
+    /// 放入一个定时回调
+    pub fn push<F: FnOnce() + Send + 'static>(&mut self, timeout: usize, f: F) -> TimerKey {
+        self.timer.push(timeout, Box::new(f))
+    }
+    /// 取消定时回调
+    pub fn cancel(&mut self, key: TimerKey) -> bool {
+        self.timer.cancel(key).is_some()
+    }
+    /// 弹出并调用指定时间内的全部到期回调
+    /// * @return `usize` 调用的回调数量
+    pub fn run_due(&mut self, now: u64) -> usize {
+        let mut count = 0;
+        while let Some(f) = self.timer.pop(now) {
+            count += 1;
+            if !self.catch_unwind {
+                f();
+            } else if catch_unwind(AssertUnwindSafe(f)).is_err() {
+                self.panic_count += 1;
+            }
+        }
+        count
+    }
+}
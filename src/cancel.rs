@@ -0,0 +1,29 @@
+//! 取消标记，其他线程无锁地标记取消，持有定时器的线程在弹出时丢弃被标记的任务，由定时器服务和共享定时器共用
+//! 在`--cfg loom`下原子量替换为loom的实现
+
+#[cfg(loom)]
+use loom::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+#[cfg(not(loom))]
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// 取消标记，可在任意线程取消定时任务，不需要访问定时器
+/// * @tip 取消只设置标记，任务仍占用定时器中的位置，直到被弹出或清理时才移除
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    /// 标记取消，无锁且不等待
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+    /// 是否已标记取消
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
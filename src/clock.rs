@@ -0,0 +1,318 @@
+//! 时钟，由时钟提供当前时间，定时器按真实时长放入和弹出定时任务
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{tick::Tick, Expired, RollReport, Timer};
+
+/// 单调时钟
+pub trait Clock {
+    /// 获得从某个固定起点开始经过的时长，不会倒退
+    fn now(&self) -> Duration;
+    /// 等待指定时长，默认为线程睡眠，由驱动循环调用
+    fn sleep(&self, dur: Duration) {
+        thread::sleep(dur);
+    }
+}
+
+/// 基于标准库Instant的时钟
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    start: Instant,
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        StdClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// 手动推进的时钟，用于测试，克隆后共享同一个时间
+/// * @tip 驱动循环的睡眠直接推进时间，不真正等待
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    /// 向后推进指定时长
+    pub fn advance(&self, dur: Duration) {
+        self.nanos
+            .fetch_add(dur.as_nanos() as u64, Ordering::Relaxed);
+    }
+    /// 设置当前时间，早于当前时间时忽略
+    pub fn set(&self, now: Duration) {
+        self.nanos
+            .fetch_max(now.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+    fn sleep(&self, dur: Duration) {
+        self.advance(dur);
+    }
+}
+
+/// 基于pi_time的时钟，使用进程启动以来的毫秒数
+#[cfg(feature = "pi_time")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PiTimeClock;
+
+#[cfg(feature = "pi_time")]
+impl Clock for PiTimeClock {
+    fn now(&self) -> Duration {
+        Duration::from_millis(pi_time::run_millis())
+    }
+}
+
+/// 驱动落后于时钟时，对积压的到期任务的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CatchUp {
+    /// 逐个交付全部积压的到期任务
+    #[default]
+    Burst,
+    /// 周期任务在积压期间只交付一次，只用于 `RecurringTimer`，ClockedTimer上的单次任务没有可合并的交付，不接受该策略
+    Coalesce,
+    /// 丢弃到期时间落后当前时间超过max_lag次滚动的任务，只交付最近的任务
+    Skip { max_lag: u64 },
+}
+
+/// 带时钟的定时器，每tick时长滚动一次
+pub struct ClockedTimer<T, C: Clock, const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<T, N0, N, L>,
+    clock: C,
+    tick: Duration,
+    origin: Duration,
+    catch_up: CatchUp,
+    skipped: u64,
+    /// `roll_elapsed` 累积的不足一次滚动的时长
+    remainder: Duration,
+}
+
+impl<T, C: Clock, const N0: usize, const N: usize, const L: usize> ClockedTimer<T, C, N0, N, L> {
+    /// 创建定时器，以时钟的当前时间为起点
+    pub fn new(clock: C, tick: Duration) -> Self {
+        assert!(!tick.is_zero(), "tick must be positive");
+        ClockedTimer {
+            timer: Default::default(),
+            origin: clock.now(),
+            clock,
+            tick,
+            catch_up: CatchUp::Burst,
+            skipped: 0,
+            remainder: Duration::ZERO,
+        }
+    }
+    /// 获得内部的定时器
+    pub fn timer(&self) -> &Timer<T, N0, N, L> {
+        &self.timer
+    }
+    /// 获得内部的定时器
+    pub fn timer_mut(&mut self) -> &mut Timer<T, N0, N, L> {
+        &mut self.timer
+    }
+    /// 获得时钟
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+    /// 设置落后时的追赶策略
+    /// * @return 策略为 `CatchUp::Coalesce` 时不设置，返回false
+    pub fn set_catch_up(&mut self, catch_up: CatchUp) -> bool {
+        if catch_up == CatchUp::Coalesce {
+            return false;
+        }
+        self.catch_up = catch_up;
+        true
+    }
+    /// 获得落后时的追赶策略
+    pub fn catch_up(&self) -> CatchUp {
+        self.catch_up
+    }
+    /// 获得按追赶策略丢弃的任务数量
+    pub fn skipped_count(&self) -> u64 {
+        self.skipped
+    }
+    /// 获得滚动一次对应的时长
+    pub fn tick(&self) -> Duration {
+        self.tick
+    }
+    /// 获得从起点经过的滚动次数
+    pub fn now(&self) -> u64 {
+        (self.elapsed().as_nanos() / self.tick.as_nanos()) as u64
+    }
+    /// 放入一个经过指定时长后到期的定时任务，到期时间向上取整到滚动次数，不会提前到期
+    pub fn push(&mut self, after: Duration, el: T) -> TimerKey {
+        let at = self.elapsed() + after;
+        let deadline = at.as_nanos().div_ceil(self.tick.as_nanos()) as u64;
+        self.timer.push_at(Tick(deadline), el)
+    }
+    /// 放入一个经过指定时长后到期的定时任务，到期时间向下取整到滚动次数，不足一次滚动的余数保留在任务中
+    /// * @tip 任务可能早于请求的时间最多一次滚动弹出，用 `residual` 获得还需等待的时长
+    #[cfg(feature = "subtick")]
+    pub fn push_precise(&mut self, after: Duration, el: T) -> TimerKey {
+        let at = self.elapsed() + after;
+        let tick = self.tick.as_nanos();
+        let deadline = (at.as_nanos() / tick) as u64;
+        let remainder = Duration::from_nanos((at.as_nanos() % tick) as u64);
+        let timeout = deadline.saturating_sub(self.timer.now().get()) as usize;
+        self.timer.push_with_remainder(timeout, el, remainder)
+    }
+    /// 获得弹出的定时任务距请求的时间还需等待的时长，已过时为0
+    #[cfg(feature = "subtick")]
+    pub fn residual(&self, e: &Expired<T>) -> Duration {
+        let at = Duration::from_nanos((self.tick.as_nanos() as u64).saturating_mul(e.deadline));
+        (at + e.remainder).saturating_sub(self.elapsed())
+    }
+    /// 取消定时任务
+    pub fn cancel(&mut self, key: TimerKey) -> Option<T> {
+        self.timer.cancel(key)
+    }
+    /// 按时钟的当前时间弹出一个到期的定时任务，按追赶策略丢弃落后过多的任务
+    pub fn pop(&mut self) -> Option<(TimerKey, T)> {
+        self.pop_expired().map(|e| (e.key, e.el))
+    }
+    /// 按时钟的当前时间弹出一个到期的定时任务及其到期信息，按追赶策略丢弃落后过多的任务
+    pub fn pop_expired(&mut self) -> Option<Expired<T>> {
+        let now = self.now();
+        loop {
+            let e = self.timer.pop_expired(now)?;
+            if let CatchUp::Skip { max_lag } = self.catch_up {
+                if e.deadline.saturating_add(max_lag) < now {
+                    self.skipped += 1;
+                    continue;
+                }
+            }
+            return Some(e);
+        }
+    }
+    /// 按驱动测得的经过时长滚动，不足一次滚动的余数累积到下次，长期运行没有漂移
+    /// * @tip 到期任务移入就绪队列，用 `timer_mut().pop_ready()` 取出；由时钟驱动的 `pop` 不应与之混用
+    pub fn roll_elapsed(&mut self, elapsed: Duration) -> RollReport {
+        let total = (self.remainder + elapsed).as_nanos();
+        let tick = self.tick.as_nanos();
+        self.remainder = Duration::from_nanos((total % tick) as u64);
+        self.timer.roll_n((total / tick) as u64)
+    }
+    /// 获得距最近的到期时间的时长，已到期时为0
+    pub fn next_timeout(&self) -> Option<Duration> {
+        let deadline = self.timer.next_expiry().map(Tick::get)?;
+        let at = Duration::from_nanos((self.tick.as_nanos() as u64).saturating_mul(deadline));
+        Some(at.saturating_sub(self.elapsed()))
+    }
+    fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_sub(self.origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        let clock = ManualClock::default();
+        clock.advance(Duration::from_micros(1500));
+        let mut timer: ClockedTimer<u32, _, 8, 8, 1> =
+            ClockedTimer::new(clock.clone(), Duration::from_millis(1));
+        clock.advance(Duration::from_micros(300));
+        timer.push(Duration::from_millis(2), 0);
+        timer.push(Duration::from_millis(10), 1);
+        assert_eq!(timer.next_timeout(), Some(Duration::from_micros(2700)));
+        clock.advance(Duration::from_millis(2));
+        assert_eq!(timer.pop(), None);
+        clock.advance(Duration::from_micros(700));
+        assert_eq!(timer.pop().map(|r| r.1), Some(0));
+        clock.set(Duration::from_millis(100));
+        assert_eq!(timer.pop().map(|r| r.1), Some(1));
+        assert_eq!(timer.next_timeout(), None);
+    }
+
+    #[test]
+    fn test_roll_elapsed() {
+        let mut timer: ClockedTimer<u32, _, 8, 8, 1> =
+            ClockedTimer::new(StdClock::default(), Duration::from_millis(3));
+        timer.timer_mut().push(10, 0);
+        // 1000次1ms恰好是333次滚动余1ms
+        let rolls: u64 = (0..1000)
+            .map(|_| timer.roll_elapsed(Duration::from_millis(1)))
+            .filter(|r| r.due > 0)
+            .count() as u64;
+        assert_eq!(rolls, 1);
+        assert_eq!(timer.timer().now().get(), 333);
+        assert_eq!(timer.timer_mut().pop_ready().map(|r| r.1), Some(0));
+        timer.roll_elapsed(Duration::from_millis(2));
+        assert_eq!(timer.timer().now().get(), 334);
+    }
+
+    #[test]
+    fn test_catch_up() {
+        let clock = ManualClock::default();
+        let mut timer: ClockedTimer<u32, _, 8, 8, 1> =
+            ClockedTimer::new(clock.clone(), Duration::from_millis(1));
+        for i in 0..10 {
+            timer.push(Duration::from_millis(i as u64 * 100), i);
+        }
+        assert!(!timer.set_catch_up(CatchUp::Coalesce));
+        assert_eq!(timer.catch_up(), CatchUp::Burst);
+        assert!(timer.set_catch_up(CatchUp::Skip { max_lag: 250 }));
+        // 驱动停顿了1秒
+        clock.advance(Duration::from_millis(1000));
+        let mut vec = Vec::new();
+        while let Some((_, v)) = timer.pop() {
+            vec.push(v);
+        }
+        assert_eq!(vec, [8, 9]);
+        assert_eq!(timer.skipped_count(), 8);
+        assert!(timer.set_catch_up(CatchUp::Burst));
+        for i in 0..3 {
+            timer.push(Duration::from_millis(i * 100), i as u32);
+        }
+        clock.advance(Duration::from_millis(1000));
+        assert_eq!(std::iter::from_fn(|| timer.pop()).count(), 3);
+    }
+
+    #[cfg(feature = "subtick")]
+    #[test]
+    fn test_subtick() {
+        let clock = ManualClock::default();
+        let mut timer: ClockedTimer<u32, _, 8, 8, 1> =
+            ClockedTimer::new(clock.clone(), Duration::from_millis(1));
+        let key = timer.push_precise(Duration::from_micros(2300), 1);
+        assert_eq!(
+            timer.timer().remainder(key),
+            Some(Duration::from_micros(300))
+        );
+        timer.push_precise(Duration::from_millis(3), 2);
+        clock.advance(Duration::from_micros(2100));
+        let e = timer.pop_expired().unwrap();
+        assert_eq!(
+            (e.el, e.deadline, e.remainder),
+            (1, 2, Duration::from_micros(300))
+        );
+        assert_eq!(timer.residual(&e), Duration::from_micros(200));
+        assert!(timer.pop_expired().is_none());
+        clock.advance(Duration::from_millis(1));
+        let e = timer.pop_expired().unwrap();
+        assert_eq!((e.el, e.remainder), (2, Duration::ZERO));
+        assert_eq!(timer.residual(&e), Duration::ZERO);
+    }
+}
@@ -0,0 +1,664 @@
+//! 异步定时，提供和futures-timer相同用法的 `Delay`，由共享的轮驱动
+//! 只依赖Waker，不假设具体的执行器，可以由使用者在自己的循环中驱动，也可以通过 `Spawner` 在执行器的阻塞线程池上驱动
+//! sink特性下 `AsyncTimer` 实现 `Sink<(Duration, F)>`，到期时在驱动线程上运行F；
+//! 延迟队列的 `DelaySink` 在同一个轮上定时，到期的元素从 `DelayStream` 流出
+
+#[cfg(feature = "sink")]
+use std::collections::VecDeque;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, OnceLock, Weak,
+    },
+    task::{Context, Poll, Waker},
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{
+    clock::{Clock, StdClock},
+    tick::Tick,
+    Timer,
+};
+
+/// 到期信号
+#[derive(Default)]
+struct Signal {
+    fired: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Signal {
+    fn fire(&self) -> Option<Waker> {
+        self.fired.store(true, Ordering::Release);
+        self.waker.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+/// 轮上的定时任务
+enum Task {
+    /// 唤醒Delay
+    Wake(Arc<Signal>),
+    /// 在驱动线程上运行，由 `Sink` 放入
+    Run(Box<dyn FnOnce() + Send>),
+}
+
+struct Inner {
+    timer: Mutex<Timer<Task, 64, 64, 3>>,
+    /// 轮中的任务数量达到上限时等待的 `Sink`
+    send_wakers: Mutex<Vec<Waker>>,
+    clock: Box<dyn Clock + Send + Sync>,
+    /// 创建时时钟的时间，为滚动次数的起点
+    origin: Duration,
+    tick: Duration,
+    /// 驱动线程，新的定时任务放入后唤醒它重新计算等待时长
+    driver: OnceLock<Thread>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // 唤醒驱动线程，使其发现定时器已释放后退出
+        if let Some(t) = self.driver.get() {
+            t.unpark();
+        }
+    }
+}
+
+/// 驱动循环的启动方式，由执行器提供可以长时间阻塞的线程
+pub trait Spawner {
+    /// 在可以阻塞的线程上运行f
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>);
+}
+
+/// 在新建的线程上运行驱动循环
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadSpawner;
+
+impl Spawner for ThreadSpawner {
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>) {
+        thread::Builder::new()
+            .name("pi_cancel_timer".to_string())
+            .spawn(f)
+            .expect("failed to spawn timer thread");
+    }
+}
+
+/// 异步定时器，克隆后指向同一个轮
+#[derive(Clone)]
+pub struct AsyncTimer {
+    inner: Arc<Inner>,
+}
+
+impl AsyncTimer {
+    /// 创建异步定时器，tick为轮滚动一次对应的时长，需要调用 `poll_timers` 驱动
+    pub fn new(tick: Duration) -> Self {
+        AsyncTimer::with_clock(StdClock::default(), tick)
+    }
+    /// 创建按指定时钟计时的异步定时器，其余同 `new`
+    /// * @tip 驱动循环按真实时间等待，时钟为 `ManualClock` 时推进后调用 `poll_timers` 即可交付
+    pub fn with_clock<C: Clock + Send + Sync + 'static>(clock: C, tick: Duration) -> Self {
+        assert!(!tick.is_zero(), "tick must be positive");
+        AsyncTimer {
+            inner: Arc::new(Inner {
+                timer: Mutex::new(Default::default()),
+                send_wakers: Mutex::new(Vec::new()),
+                origin: clock.now(),
+                clock: Box::new(clock),
+                tick,
+                driver: OnceLock::new(),
+            }),
+        }
+    }
+    /// 获得全局的异步定时器，精度为1毫秒，由一个后台线程驱动
+    pub fn global() -> &'static AsyncTimer {
+        static GLOBAL: OnceLock<AsyncTimer> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let timer = AsyncTimer::new(Duration::from_millis(1));
+            timer.spawn_driver(&ThreadSpawner);
+            timer
+        })
+    }
+    /// 通过spawner启动驱动循环，定时器的全部克隆和Delay释放后循环退出
+    /// * @tip 一个定时器只应启动一个驱动循环
+    pub fn spawn_driver<S: Spawner + ?Sized>(&self, spawner: &S) {
+        let weak = Arc::downgrade(&self.inner);
+        spawner.spawn_blocking(Box::new(move || drive(weak)));
+    }
+    /// 获得从创建起经过的滚动次数
+    pub fn now(&self) -> u64 {
+        (self.elapsed().as_nanos() / self.inner.tick.as_nanos()) as u64
+    }
+    /// 创建一个经过指定时长后完成的Delay
+    pub fn delay(&self, dur: Duration) -> Delay {
+        let mut delay = Delay {
+            timer: self.clone(),
+            key: None,
+            signal: Default::default(),
+        };
+        delay.reset(dur);
+        delay
+    }
+    /// 设置 `Sink` 放入的任务数量上限，轮中的任务数量达到上限时 `poll_ready` 等待，默认不限制
+    /// * @tip Delay不受上限限制，但计入轮中的任务数量
+    pub fn set_capacity(&self, capacity: usize) {
+        self.lock().set_max_pending(capacity);
+    }
+    /// 弹出全部到期的定时任务，唤醒对应的Delay，运行 `Sink` 放入的任务
+    /// * @return `usize` 唤醒和运行的数量
+    pub fn poll_timers(&self) -> usize {
+        let now = self.now();
+        let mut wakers = Vec::new();
+        let mut runs = Vec::new();
+        {
+            let mut timer = self.lock();
+            while let Some(task) = timer.pop(now) {
+                match task {
+                    Task::Wake(signal) => wakers.extend(signal.fire()),
+                    Task::Run(f) => runs.push(f),
+                }
+            }
+        }
+        let count = wakers.len() + runs.len();
+        if count > 0 {
+            wakers.append(
+                &mut self
+                    .inner
+                    .send_wakers
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner()),
+            );
+        }
+        // 在锁外唤醒和运行，避免被唤醒的任务在本线程上重入
+        for f in runs {
+            f();
+        }
+        for w in wakers {
+            w.wake();
+        }
+        count
+    }
+    /// 获得下一次需要调用 `poll_timers` 的时刻
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        let deadline = self.lock().next_expiry().map(Tick::get)?;
+        let at = Duration::from_nanos((self.inner.tick.as_nanos() as u64).saturating_mul(deadline));
+        Some(Instant::now() + at.saturating_sub(self.elapsed()))
+    }
+    /// 创建延迟队列，队列中的元素数量达到capacity时 `DelaySink` 等待，usize::MAX表示不限制
+    #[cfg(feature = "sink")]
+    pub fn delay_queue<T: Send + 'static>(
+        &self,
+        capacity: usize,
+    ) -> (DelaySink<T>, DelayStream<T>) {
+        let state = Arc::new(Mutex::new(QueueState {
+            ready: VecDeque::new(),
+            pending: 0,
+            capacity,
+            closed: false,
+            receiver: true,
+            send_waker: None,
+            recv_waker: None,
+        }));
+        (
+            DelaySink {
+                timer: self.clone(),
+                state: state.clone(),
+            },
+            DelayStream { state },
+        )
+    }
+    /// 按绝对时间向上取整，获得经过指定时长后的滚动次数
+    fn deadline(&self, dur: Duration) -> u64 {
+        let at = self.elapsed() + dur;
+        at.as_nanos().div_ceil(self.inner.tick.as_nanos()) as u64
+    }
+    fn elapsed(&self) -> Duration {
+        self.inner.clock.now().saturating_sub(self.inner.origin)
+    }
+    fn lock(&self) -> MutexGuard<'_, Timer<Task, 64, 64, 3>> {
+        self.inner.timer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 驱动循环，在最近的到期时刻弹出到期任务，放入更早的任务时被唤醒
+fn drive(weak: Weak<Inner>) {
+    match weak.upgrade() {
+        Some(inner) => {
+            let _ = inner.driver.set(thread::current());
+        }
+        None => return,
+    }
+    while let Some(inner) = weak.upgrade() {
+        let timer = AsyncTimer { inner };
+        timer.poll_timers();
+        let wakeup = timer.next_wakeup();
+        drop(timer);
+        match wakeup {
+            Some(at) => thread::park_timeout(at.saturating_duration_since(Instant::now())),
+            None => thread::park(),
+        }
+    }
+}
+
+/// 经过指定时长后完成的Future
+pub struct Delay {
+    timer: AsyncTimer,
+    key: Option<TimerKey>,
+    signal: Arc<Signal>,
+}
+
+impl Delay {
+    /// 在全局的异步定时器上创建Delay
+    pub fn new(dur: Duration) -> Delay {
+        AsyncTimer::global().delay(dur)
+    }
+    /// 从现在起重新计时，已完成的Delay也可以重新使用
+    pub fn reset(&mut self, dur: Duration) {
+        // 按绝对时间向上取整，保证不会提前完成
+        let deadline = self.timer.deadline(dur);
+        let signal = Arc::new(Signal::default());
+        {
+            let mut timer = self.timer.lock();
+            if let Some(key) = self.key.take() {
+                timer.cancel(key);
+            }
+            self.key = Some(timer.push_at(Tick(deadline), Task::Wake(signal.clone())));
+        }
+        // 转移已登记的Waker，重置后不需要重新poll也能被唤醒
+        let waker = self
+            .signal
+            .waker
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        *signal.waker.lock().unwrap_or_else(|e| e.into_inner()) = waker;
+        self.signal = signal;
+        if let Some(t) = self.timer.inner.driver.get() {
+            t.unpark();
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.signal.fired.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+        {
+            let mut waker = self.signal.waker.lock().unwrap_or_else(|e| e.into_inner());
+            match &mut *waker {
+                Some(w) if w.will_wake(cx.waker()) => (),
+                w => *w = Some(cx.waker().clone()),
+            }
+        }
+        // 登记Waker后再检查一次，避免错过登记期间的到期
+        if self.signal.fired.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            if !self.signal.fired.load(Ordering::Acquire) {
+                self.timer.lock().cancel(key);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sink")]
+impl<F: FnOnce() + Send + 'static> futures_sink::Sink<(Duration, F)> for AsyncTimer {
+    type Error = std::convert::Infallible;
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let timer = self.lock();
+        if timer.len() < timer.max_pending() {
+            return Poll::Ready(Ok(()));
+        }
+        // 持有轮的锁时登记，不会错过驱动线程弹出任务后的唤醒
+        self.inner
+            .send_wakers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(cx.waker().clone());
+        Poll::Pending
+    }
+    fn start_send(self: Pin<&mut Self>, (dur, f): (Duration, F)) -> Result<(), Self::Error> {
+        let deadline = self.deadline(dur);
+        // 容量由poll_ready检查，这里不拒绝
+        self.lock().push_at(Tick(deadline), Task::Run(Box::new(f)));
+        if let Some(t) = self.inner.driver.get() {
+            t.unpark();
+        }
+        Ok(())
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// 延迟队列的共享状态
+#[cfg(feature = "sink")]
+struct QueueState<T> {
+    /// 已到期、等待接收端取走的元素
+    ready: VecDeque<T>,
+    /// 在轮中尚未到期的元素数量
+    pending: usize,
+    /// 未到期和未取走的元素数量上限
+    capacity: usize,
+    /// 发送端已关闭
+    closed: bool,
+    /// 接收端还存在
+    receiver: bool,
+    /// 队列满时等待的发送端
+    send_waker: Option<Waker>,
+    /// 等待新元素的接收端
+    recv_waker: Option<Waker>,
+}
+
+/// 延迟队列的发送端，放入 `(时长, 元素)`，元素在经过时长后从接收端流出
+/// * @tip 元素放入 `AsyncTimer` 的轮中，由其驱动循环按到期顺序移入接收端；
+///   队列满时 `poll_ready` 等待接收端取走元素；接收端释放后发送返回 `PushError::Shutdown`
+#[cfg(feature = "sink")]
+pub struct DelaySink<T> {
+    timer: AsyncTimer,
+    state: Arc<Mutex<QueueState<T>>>,
+}
+
+/// 延迟队列的接收端，按到期顺序流出元素，发送端关闭且队列为空时结束
+#[cfg(feature = "sink")]
+pub struct DelayStream<T> {
+    state: Arc<Mutex<QueueState<T>>>,
+}
+
+#[cfg(feature = "sink")]
+fn lock_state<T>(state: &Mutex<QueueState<T>>) -> MutexGuard<'_, QueueState<T>> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(feature = "sink")]
+impl<T: Send + 'static> futures_sink::Sink<(Duration, T)> for DelaySink<T> {
+    type Error = crate::PushError<T>;
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut state = lock_state(&self.state);
+        if !state.receiver || state.pending + state.ready.len() < state.capacity {
+            return Poll::Ready(Ok(()));
+        }
+        state.send_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+    fn start_send(mut self: Pin<&mut Self>, (dur, el): (Duration, T)) -> Result<(), Self::Error> {
+        {
+            let mut state = lock_state(&self.state);
+            if !state.receiver || state.closed {
+                return Err(crate::PushError::Shutdown(el));
+            }
+            state.pending += 1;
+        }
+        let state = self.state.clone();
+        let run = move || {
+            let mut state = lock_state(&state);
+            state.pending -= 1;
+            if state.receiver {
+                state.ready.push_back(el);
+            }
+            if let Some(w) = state.recv_waker.take() {
+                w.wake();
+            }
+        };
+        let Ok(()) = Pin::new(&mut self.timer).start_send((dur, run));
+        Ok(())
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut state = lock_state(&self.state);
+        state.closed = true;
+        if let Some(w) = state.recv_waker.take() {
+            w.wake();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "sink")]
+impl<T> Drop for DelaySink<T> {
+    fn drop(&mut self) {
+        let mut state = lock_state(&self.state);
+        state.closed = true;
+        if let Some(w) = state.recv_waker.take() {
+            w.wake();
+        }
+    }
+}
+
+#[cfg(feature = "sink")]
+impl<T> futures_core::Stream for DelayStream<T> {
+    type Item = T;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = lock_state(&self.state);
+        if let Some(el) = state.ready.pop_front() {
+            if let Some(w) = state.send_waker.take() {
+                w.wake();
+            }
+            return Poll::Ready(Some(el));
+        }
+        if state.closed && state.pending == 0 {
+            return Poll::Ready(None);
+        }
+        state.recv_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "sink")]
+impl<T> Drop for DelayStream<T> {
+    fn drop(&mut self) {
+        let mut state = lock_state(&self.state);
+        state.receiver = false;
+        state.ready.clear();
+        if let Some(w) = state.send_waker.take() {
+            w.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_delay() {
+        use std::{
+            pin::pin,
+            sync::atomic::AtomicUsize,
+            task::{Poll, Wake},
+        };
+        struct Count(AtomicUsize);
+        impl Wake for Count {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        let count = Arc::new(Count(AtomicUsize::new(0)));
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+        // 使用者驱动
+        let clock = ManualClock::default();
+        let timer = AsyncTimer::with_clock(clock.clone(), Duration::from_millis(1));
+        let mut d = pin!(timer.delay(Duration::from_millis(5)));
+        assert_eq!(d.as_mut().poll(&mut cx), Poll::Pending);
+        clock.advance(Duration::from_millis(4));
+        assert_eq!(timer.poll_timers(), 0);
+        assert_eq!(d.as_mut().poll(&mut cx), Poll::Pending);
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(timer.poll_timers(), 1);
+        assert_eq!(count.0.load(Ordering::SeqCst), 1);
+        assert_eq!(d.as_mut().poll(&mut cx), Poll::Ready(()));
+        d.as_mut().reset(Duration::from_millis(2));
+        assert_eq!(d.as_mut().poll(&mut cx), Poll::Pending);
+        // 释放的Delay从轮中取消，不会被唤醒
+        drop(timer.delay(Duration::from_millis(1)));
+        clock.advance(Duration::from_millis(2));
+        assert_eq!(timer.poll_timers(), 1);
+        assert_eq!(count.0.load(Ordering::SeqCst), 2);
+        assert_eq!(d.as_mut().poll(&mut cx), Poll::Ready(()));
+        assert_eq!(timer.next_wakeup(), None);
+        // 全局驱动线程
+        smol::block_on(Delay::new(Duration::from_millis(3)));
+    }
+
+    async fn delays(timer: AsyncTimer, clock: ManualClock) {
+        let start = clock.now();
+        let a = timer.delay(Duration::from_millis(20));
+        let mut b = timer.delay(Duration::from_millis(5));
+        b.await;
+        assert!(clock.now() - start >= Duration::from_millis(5));
+        b = timer.delay(Duration::from_millis(1));
+        b.reset(Duration::from_millis(10));
+        b.await;
+        a.await;
+        assert!(clock.now() - start >= Duration::from_millis(20));
+    }
+
+    /// 推进时钟并弹出到期任务，直到done返回true
+    fn advance_until(timer: &AsyncTimer, clock: &ManualClock, mut done: impl FnMut() -> bool) {
+        while !done() {
+            clock.advance(Duration::from_millis(1));
+            timer.poll_timers();
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_delay_tokio() {
+        struct TokioSpawner(tokio::runtime::Handle);
+        impl Spawner for TokioSpawner {
+            fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>) {
+                self.0.spawn_blocking(f);
+            }
+        }
+        let rt = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+        let clock = ManualClock::default();
+        let timer = AsyncTimer::with_clock(clock.clone(), Duration::from_millis(1));
+        timer.spawn_driver(&TokioSpawner(rt.handle().clone()));
+        let tasks: Vec<_> = (0..4)
+            .map(|_| rt.spawn(delays(timer.clone(), clock.clone())))
+            .collect();
+        advance_until(&timer, &clock, || tasks.iter().all(|t| t.is_finished()));
+        for t in tasks {
+            rt.block_on(t).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_delay_smol() {
+        struct SmolSpawner;
+        impl Spawner for SmolSpawner {
+            fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send + 'static>) {
+                smol::unblock(f).detach();
+            }
+        }
+        let clock = ManualClock::default();
+        let timer = AsyncTimer::with_clock(clock.clone(), Duration::from_millis(1));
+        timer.spawn_driver(&SmolSpawner);
+        let tasks: Vec<_> = (0..4)
+            .map(|_| smol::spawn(delays(timer.clone(), clock.clone())))
+            .collect();
+        advance_until(&timer, &clock, || tasks.iter().all(|t| t.is_finished()));
+        for t in tasks {
+            smol::block_on(t);
+        }
+    }
+
+    #[cfg(feature = "sink")]
+    #[test]
+    fn test_delay_queue() {
+        use futures_sink::Sink;
+        use smol::stream::StreamExt;
+        let clock = ManualClock::default();
+        let timer = AsyncTimer::with_clock(clock.clone(), Duration::from_millis(1));
+        let (mut sink, mut stream) = timer.delay_queue(2);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut sink = Pin::new(&mut sink);
+        assert!(sink.as_mut().poll_ready(&mut cx).is_ready());
+        sink.as_mut()
+            .start_send((Duration::from_millis(40), 1))
+            .unwrap();
+        sink.as_mut()
+            .start_send((Duration::from_millis(5), 2))
+            .unwrap();
+        // 队列已满，等待接收端取走元素
+        assert!(sink.as_mut().poll_ready(&mut cx).is_pending());
+        clock.advance(Duration::from_millis(5));
+        timer.poll_timers();
+        assert!(sink.as_mut().poll_ready(&mut cx).is_pending());
+        assert_eq!(smol::block_on(stream.next()), Some(2));
+        assert!(sink.as_mut().poll_ready(&mut cx).is_ready());
+        sink.as_mut()
+            .start_send((Duration::from_millis(1), 3))
+            .unwrap();
+        assert!(sink.as_mut().poll_close(&mut cx).is_ready());
+        assert!(sink.as_mut().start_send((Duration::ZERO, 4)).is_err());
+        clock.advance(Duration::from_millis(1));
+        timer.poll_timers();
+        assert_eq!(smol::block_on(stream.next()), Some(3));
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        clock.advance(Duration::from_millis(34));
+        timer.poll_timers();
+        assert_eq!(smol::block_on(stream.next()), Some(1));
+        assert_eq!(smol::block_on(stream.next()), None);
+    }
+
+    #[cfg(feature = "sink")]
+    #[test]
+    fn test_async_timer_sink() {
+        use futures_sink::Sink;
+        use std::{sync::mpsc::channel, task::Wake};
+        type Work = Box<dyn FnOnce() + Send>;
+        struct Woken(AtomicBool);
+        impl Wake for Woken {
+            fn wake(self: Arc<Self>) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+        let woken = Arc::new(Woken(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        let clock = ManualClock::default();
+        let mut timer = AsyncTimer::with_clock(clock.clone(), Duration::from_millis(1));
+        timer.set_capacity(2);
+        let (s, r) = channel();
+        let mut sink = Pin::new(&mut timer);
+        for i in 0..2 {
+            assert!(Sink::<(Duration, Work)>::poll_ready(sink.as_mut(), &mut cx).is_ready());
+            let s = s.clone();
+            let work: Work = Box::new(move || s.send(i).unwrap());
+            let Ok(()) = sink
+                .as_mut()
+                .start_send((Duration::from_millis(4 - 2 * i), work));
+        }
+        // 轮中的任务达到上限，等待驱动运行到期的任务
+        assert!(Sink::<(Duration, Work)>::poll_ready(sink.as_mut(), &mut cx).is_pending());
+        clock.advance(Duration::from_millis(2));
+        assert_eq!(timer.poll_timers(), 1);
+        assert_eq!(r.try_iter().collect::<Vec<_>>(), [1]);
+        assert!(woken.0.load(Ordering::SeqCst));
+        clock.advance(Duration::from_millis(2));
+        assert_eq!(timer.poll_timers(), 1);
+        assert_eq!(r.try_iter().collect::<Vec<_>>(), [0]);
+        let mut sink = Pin::new(&mut timer);
+        assert!(Sink::<(Duration, Work)>::poll_ready(sink.as_mut(), &mut cx).is_ready());
+    }
+}
@@ -0,0 +1,181 @@
+//! 到期任务的交付方式，把“何时到期”和“结果如何到达应用”分开，驱动只负责按时弹出，交付由 `Deliver` 实现
+
+use std::{
+    collections::VecDeque,
+    ops::ControlFlow,
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+use pi_slot_wheel::TimerKey;
+
+/// 到期任务的交付
+pub trait Deliver<T> {
+    /// 交付一个到期任务，deadline为到期时的滚动次数
+    /// * @return 返回Break时驱动停止，如通道的接收端已全部释放
+    fn deliver(&mut self, key: TimerKey, deadline: u64, el: T) -> ControlFlow<()>;
+}
+
+/// 回调函数
+impl<T, F: FnMut(TimerKey, u64, T) -> ControlFlow<()>> Deliver<T> for F {
+    fn deliver(&mut self, key: TimerKey, deadline: u64, el: T) -> ControlFlow<()> {
+        self(key, deadline, el)
+    }
+}
+
+/// 收集到Vec中
+impl<T> Deliver<T> for Vec<(TimerKey, u64, T)> {
+    fn deliver(&mut self, key: TimerKey, deadline: u64, el: T) -> ControlFlow<()> {
+        self.push((key, deadline, el));
+        ControlFlow::Continue(())
+    }
+}
+
+/// 发送到标准库的通道，接收端释放时停止
+impl<T> Deliver<T> for mpsc::Sender<(TimerKey, T)> {
+    fn deliver(&mut self, key: TimerKey, _deadline: u64, el: T) -> ControlFlow<()> {
+        match self.send((key, el)) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    }
+}
+
+/// 发送到标准库的有界通道，通道满时阻塞，接收端释放时停止
+impl<T> Deliver<T> for mpsc::SyncSender<(TimerKey, T)> {
+    fn deliver(&mut self, key: TimerKey, _deadline: u64, el: T) -> ControlFlow<()> {
+        match self.send((key, el)) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    }
+}
+
+/// 发送到crossbeam通道，通道满时阻塞，接收端释放时停止
+#[cfg(feature = "crossbeam")]
+impl<T> Deliver<T> for crossbeam_channel::Sender<(TimerKey, T)> {
+    fn deliver(&mut self, key: TimerKey, _deadline: u64, el: T) -> ControlFlow<()> {
+        match self.send((key, el)) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    }
+}
+
+/// 唤醒队列的内容，到期任务和等待的Waker
+type Queue<T> = (VecDeque<(TimerKey, T)>, Option<Waker>);
+
+/// 唤醒队列，驱动放入到期任务并唤醒等待的异步任务，克隆后指向同一个队列
+pub struct WakeQueue<T> {
+    inner: Arc<Mutex<Queue<T>>>,
+}
+
+impl<T> Clone for WakeQueue<T> {
+    fn clone(&self) -> Self {
+        WakeQueue {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Default for WakeQueue<T> {
+    fn default() -> Self {
+        WakeQueue {
+            inner: Arc::new(Mutex::new((VecDeque::new(), None))),
+        }
+    }
+}
+
+impl<T> WakeQueue<T> {
+    /// 取出一个到期任务，队列为空时登记Waker，下次交付时唤醒
+    pub fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<(TimerKey, T)> {
+        let mut inner = self.lock();
+        match inner.0.pop_front() {
+            Some(r) => Poll::Ready(r),
+            None => {
+                inner.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+    /// 取出一个到期任务，不等待
+    pub fn try_pop(&self) -> Option<(TimerKey, T)> {
+        self.lock().0.pop_front()
+    }
+    /// 获得队列中的到期任务数量
+    pub fn len(&self) -> usize {
+        self.lock().0.len()
+    }
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.lock().0.is_empty()
+    }
+    fn lock(&self) -> MutexGuard<'_, Queue<T>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Deliver<T> for WakeQueue<T> {
+    fn deliver(&mut self, key: TimerKey, _deadline: u64, el: T) -> ControlFlow<()> {
+        let waker = {
+            let mut inner = self.lock();
+            inner.0.push_back((key, el));
+            inner.1.take()
+        };
+        // 在锁外唤醒，避免被唤醒的任务在本线程上重入
+        if let Some(w) = waker {
+            w.wake();
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_deliver() {
+        use crate::clock::{ClockedTimer, ManualClock};
+        use crate::driver::{run_deliver, PreciseConfig};
+        use std::ops::ControlFlow;
+        let clock = ManualClock::default();
+        let mut timer: ClockedTimer<u32, _, 8, 8, 1> =
+            ClockedTimer::new(clock.clone(), Duration::from_millis(1));
+        let push = |timer: &mut ClockedTimer<u32, _, 8, 8, 1>| {
+            for i in 0..3 {
+                timer.push(Duration::from_millis(i), i as u32);
+            }
+        };
+        let config = PreciseConfig::default();
+        clock.advance(Duration::from_millis(5));
+        push(&mut timer);
+        clock.advance(Duration::from_millis(10));
+        let mut vec = Vec::new();
+        assert_eq!(run_deliver(&mut timer, config, &mut vec), 3);
+        assert_eq!(
+            vec.iter().map(|r| (r.1, r.2)).collect::<Vec<_>>(),
+            [(5, 0), (6, 1), (7, 2)]
+        );
+        push(&mut timer);
+        clock.advance(Duration::from_millis(10));
+        let mut n = 0;
+        let mut f = |_, _, _| {
+            n += 1;
+            ControlFlow::Break(())
+        };
+        assert_eq!(run_deliver(&mut timer, config, &mut f), 1);
+        let (tx, rx) = std::sync::mpsc::channel();
+        drop(rx);
+        let mut tx: Box<dyn Deliver<u32>> = Box::new(tx);
+        assert_eq!(run_deliver(&mut timer, config, &mut *tx), 1);
+        let queue = WakeQueue::default();
+        let q = queue.clone();
+        let h =
+            std::thread::spawn(move || smol::block_on(std::future::poll_fn(|cx| q.poll_pop(cx))).1);
+        assert_eq!(run_deliver(&mut timer, config, &mut queue.clone()), 1);
+        assert_eq!(h.join().unwrap(), 2);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+//! 驱动循环，按时钟等待最近的到期时间并处理到期任务
+
+use std::{hint, ops::ControlFlow, time::Duration};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{
+    clock::{Clock, ClockedTimer},
+    deliver::Deliver,
+    RollReport, Timer,
+};
+
+/// 高精度驱动的配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreciseConfig {
+    /// 距到期时间小于该值时改为自旋等待，默认1毫秒
+    pub spin_threshold: Duration,
+    /// 单次睡眠的最长时间，默认100毫秒
+    pub max_sleep: Duration,
+}
+
+impl Default for PreciseConfig {
+    fn default() -> Self {
+        PreciseConfig {
+            spin_threshold: Duration::from_millis(1),
+            max_sleep: Duration::from_millis(100),
+        }
+    }
+}
+
+/// 高精度驱动，先睡眠到距到期时间约spin_threshold处，再自旋等待到期，处理全部到期任务
+/// * @tip 睡眠由时钟的 `sleep` 完成，自旋等待时时钟必须随真实时间前进；定时器为空或handler返回Break时退出
+/// * @return `usize` 处理的任务数量
+pub fn run_precise<T, C, F, const N0: usize, const N: usize, const L: usize>(
+    timer: &mut ClockedTimer<T, C, N0, N, L>,
+    config: PreciseConfig,
+    mut handler: F,
+) -> usize
+where
+    C: Clock,
+    F: FnMut(TimerKey, T) -> ControlFlow<()>,
+{
+    run_deliver(timer, config, &mut |key, _, el| handler(key, el))
+}
+
+/// 高精度驱动，等待方式同 `run_precise`，到期任务交给deliver交付
+/// * @tip 定时器为空或deliver返回Break时退出
+/// * @return `usize` 交付的任务数量
+pub fn run_deliver<T, C, D, const N0: usize, const N: usize, const L: usize>(
+    timer: &mut ClockedTimer<T, C, N0, N, L>,
+    config: PreciseConfig,
+    deliver: &mut D,
+) -> usize
+where
+    C: Clock,
+    D: Deliver<T> + ?Sized,
+{
+    let mut count = 0;
+    loop {
+        while let Some(e) = timer.pop_expired() {
+            count += 1;
+            if deliver.deliver(e.key, e.deadline, e.el).is_break() {
+                return count;
+            }
+        }
+        if !wait_due(timer, &config) {
+            return count;
+        }
+    }
+}
+
+/// 等待最近的到期时间，可能因单次睡眠的上限提前返回
+/// * @return 定时器为空时返回false
+fn wait_due<T, C: Clock, const N0: usize, const N: usize, const L: usize>(
+    timer: &ClockedTimer<T, C, N0, N, L>,
+    config: &PreciseConfig,
+) -> bool {
+    let wait = match timer.next_timeout() {
+        Some(wait) => wait,
+        None => return false,
+    };
+    if wait > config.spin_threshold {
+        timer
+            .clock()
+            .sleep((wait - config.spin_threshold).min(config.max_sleep));
+        return true;
+    }
+    while timer.next_timeout().is_some_and(|w| !w.is_zero()) {
+        hint::spin_loop();
+    }
+    true
+}
+
+/// 滚动驱动，每睡眠一个tick滚动一次定时器
+/// * @tip 睡眠总会多出一些时间，长时间运行后滚动次数会落后于真实时间；
+///   设置校正间隔后，每滚动若干次按时钟校正一次，落后时补滚，超前时跳过后续的滚动
+pub struct TickDriver<C: Clock> {
+    clock: C,
+    tick: Duration,
+    /// 每滚动多少次校正一次，0为不校正
+    reanchor: u64,
+    origin: Duration,
+    /// 从起点开始已滚动的次数
+    rolled: u64,
+    /// 距上次校正的步数
+    steps: u64,
+    /// 超前时待跳过的步数
+    skip: u64,
+    /// 累计校正的滚动次数，正数为补滚，负数为跳过
+    correction: i64,
+    /// 最近一次校正的滚动次数
+    last_correction: i64,
+}
+
+impl<C: Clock> TickDriver<C> {
+    /// 创建驱动，以时钟的当前时间为起点，默认不校正
+    pub fn new(clock: C, tick: Duration) -> Self {
+        assert!(!tick.is_zero(), "tick must be positive");
+        TickDriver {
+            origin: clock.now(),
+            clock,
+            tick,
+            reanchor: 0,
+            rolled: 0,
+            steps: 0,
+            skip: 0,
+            correction: 0,
+            last_correction: 0,
+        }
+    }
+    /// 设置每滚动多少次按时钟校正一次，0为不校正
+    pub fn set_reanchor(&mut self, reanchor: u64) {
+        self.reanchor = reanchor;
+    }
+    /// 获得校正间隔
+    pub fn reanchor(&self) -> u64 {
+        self.reanchor
+    }
+    /// 获得累计校正的滚动次数，正数为补滚，负数为跳过
+    pub fn correction(&self) -> i64 {
+        self.correction
+    }
+    /// 获得最近一次校正的滚动次数
+    pub fn last_correction(&self) -> i64 {
+        self.last_correction
+    }
+    /// 获得从起点开始已滚动的次数
+    pub fn rolled(&self) -> u64 {
+        self.rolled
+    }
+    /// 前进一步，不睡眠：滚动一次，超前时跳过，到达校正间隔时按时钟校正
+    /// * @return `RollReport` 本步的滚动报告，包括补滚
+    pub fn step<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        timer: &mut Timer<T, N0, N, L>,
+    ) -> RollReport {
+        let mut report = if self.skip > 0 {
+            self.skip -= 1;
+            RollReport::default()
+        } else {
+            self.rolled += 1;
+            timer.roll_n(1)
+        };
+        self.steps += 1;
+        if self.reanchor == 0 || self.steps < self.reanchor {
+            return report;
+        }
+        self.steps = 0;
+        let expected = ((self.clock.now().saturating_sub(self.origin)).as_nanos()
+            / self.tick.as_nanos()) as u64;
+        // 待跳过的步数视为已扣除，落后时先抵消待跳过的步数，再补滚
+        let behind = expected as i64 - (self.rolled - self.skip) as i64;
+        if behind < 0 {
+            self.skip += behind.unsigned_abs();
+        } else {
+            let cancel = self.skip.min(behind as u64);
+            self.skip -= cancel;
+            let extra = behind as u64 - cancel;
+            if extra > 0 {
+                let r = timer.roll_n(extra);
+                report.due += r.due;
+                report.cascaded += r.cascaded;
+                report.wrapped |= r.wrapped;
+                self.rolled += extra;
+            }
+        }
+        self.last_correction = behind;
+        self.correction += behind;
+        report
+    }
+    /// 驱动定时器，每睡眠一个tick前进一步，弹出全部到期任务交给handler
+    /// * @tip 定时器为空或handler返回Break时退出
+    /// * @return `usize` 处理的任务数量
+    pub fn run<T, F, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        timer: &mut Timer<T, N0, N, L>,
+        mut handler: F,
+    ) -> usize
+    where
+        F: FnMut(TimerKey, T) -> ControlFlow<()>,
+    {
+        let mut count = 0;
+        while !timer.is_empty() {
+            self.clock.sleep(self.tick);
+            self.step(timer);
+            while let Some((key, el)) = timer.pop_ready() {
+                count += 1;
+                if handler(key, el).is_break() {
+                    return count;
+                }
+            }
+        }
+        count
+    }
+}
+
+/// 通道满时的处理方式
+#[cfg(feature = "crossbeam")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullPolicy {
+    /// 阻塞等待通道有空位
+    #[default]
+    Block,
+    /// 丢弃到期任务并计数
+    Drop,
+    /// 放回定时器，下一次滚动时再交付
+    Requeue,
+}
+
+/// 通道驱动的报告
+#[cfg(feature = "crossbeam")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelReport {
+    /// 交付的任务数量
+    pub delivered: usize,
+    /// 通道满时丢弃的任务数量
+    pub dropped: usize,
+    /// 通道满时放回定时器的次数
+    pub requeued: usize,
+}
+
+/// 通道驱动，把到期任务发送到调用方提供的crossbeam通道，等待方式同 `run_precise`
+/// * @tip 定时器为空或通道的接收端全部释放时退出，放回的任务会得到新的关键字
+#[cfg(feature = "crossbeam")]
+pub fn run_channel<T, C, const N0: usize, const N: usize, const L: usize>(
+    timer: &mut ClockedTimer<T, C, N0, N, L>,
+    config: PreciseConfig,
+    sender: &crossbeam_channel::Sender<(TimerKey, T)>,
+    policy: FullPolicy,
+) -> ChannelReport
+where
+    C: Clock,
+{
+    use crossbeam_channel::TrySendError;
+    let mut report = ChannelReport::default();
+    loop {
+        while let Some(item) = timer.pop() {
+            let r = match policy {
+                FullPolicy::Block => sender
+                    .send(item)
+                    .map_err(|e| TrySendError::Disconnected(e.0)),
+                _ => sender.try_send(item),
+            };
+            match r {
+                Ok(()) => report.delivered += 1,
+                Err(TrySendError::Disconnected(_)) => return report,
+                Err(TrySendError::Full(_)) if policy == FullPolicy::Drop => report.dropped += 1,
+                Err(TrySendError::Full((_, el))) => {
+                    // 放回下一次滚动，本轮不再交付
+                    let tick = timer.tick();
+                    timer.push(tick, el);
+                    report.requeued += 1;
+                    break;
+                }
+            }
+        }
+        if !wait_due(timer, &config) {
+            return report;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "crossbeam")]
+    #[test]
+    fn test_run_channel() {
+        use crate::clock::{ClockedTimer, ManualClock};
+        let clock = ManualClock::default();
+        let new = || {
+            let mut timer: ClockedTimer<u32, _, 8, 8, 1> =
+                ClockedTimer::new(clock.clone(), Duration::from_millis(1));
+            for i in 0..3 {
+                timer.push(Duration::ZERO, i);
+            }
+            timer
+        };
+        let (tx, rx) = crossbeam_channel::bounded(2);
+        let r = run_channel(&mut new(), PreciseConfig::default(), &tx, FullPolicy::Drop);
+        assert_eq!(
+            r,
+            ChannelReport {
+                delivered: 2,
+                dropped: 1,
+                requeued: 0
+            }
+        );
+        assert_eq!(rx.try_iter().map(|r| r.1).collect::<Vec<_>>(), [0, 1]);
+        // 放回的任务在时钟前进后交付
+        let c = clock.clone();
+        let h = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            let mut r = vec![rx.recv().unwrap().1, rx.recv().unwrap().1];
+            c.advance(Duration::from_millis(1));
+            r.push(rx.recv().unwrap().1);
+            r
+        });
+        let r = run_channel(
+            &mut new(),
+            PreciseConfig::default(),
+            &tx,
+            FullPolicy::Requeue,
+        );
+        assert_eq!(
+            r,
+            ChannelReport {
+                delivered: 3,
+                dropped: 0,
+                requeued: 1
+            }
+        );
+        assert_eq!(h.join().unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_tick_driver() {
+        use crate::clock::ManualClock;
+        let clock = ManualClock::default();
+        let mut driver = TickDriver::new(clock.clone(), Duration::from_millis(1));
+        driver.set_reanchor(4);
+        let mut timer: Timer<u32, 8, 8, 1> = Default::default();
+        timer.push(6, 1);
+        // 睡眠偏长，4步经过了6毫秒，校正时补滚2次
+        clock.advance(Duration::from_millis(6));
+        for _ in 0..3 {
+            driver.step(&mut timer);
+        }
+        assert_eq!(driver.correction(), 0);
+        assert_eq!(driver.step(&mut timer).due, 1);
+        assert_eq!(driver.last_correction(), 2);
+        assert_eq!(timer.now().get(), 6);
+        assert_eq!(timer.pop_ready().map(|r| r.1), Some(1));
+        // 时钟停止，超前的4次滚动在后续的步中跳过
+        for _ in 0..4 {
+            driver.step(&mut timer);
+        }
+        assert_eq!(driver.last_correction(), -4);
+        clock.advance(Duration::from_millis(4));
+        for _ in 0..4 {
+            driver.step(&mut timer);
+        }
+        assert_eq!(timer.now().get(), 10);
+        assert_eq!(driver.rolled(), 10);
+        assert_eq!(driver.correction(), -2);
+        assert_eq!(driver.last_correction(), 0);
+    }
+
+    #[test]
+    fn test_run_precise() {
+        use crate::clock::{Clock, ClockedTimer, ManualClock};
+        use std::ops::ControlFlow;
+        let clock = ManualClock::default();
+        let mut timer: ClockedTimer<Duration, _, 64, 64, 1> =
+            ClockedTimer::new(clock.clone(), Duration::from_micros(100));
+        for ms in [3, 1, 2, 5] {
+            timer.push(
+                Duration::from_millis(ms),
+                clock.now() + Duration::from_millis(ms),
+            );
+        }
+        // 手动时钟不随真实时间前进，不自旋，睡眠直接推进时钟
+        let config = PreciseConfig {
+            spin_threshold: Duration::ZERO,
+            ..Default::default()
+        };
+        let mut last = Duration::ZERO;
+        let count = run_precise(&mut timer, config, |_, at| {
+            let now = clock.now();
+            // 不会提前，且按到期时间顺序处理
+            assert!(now >= at, "{:?} < {:?}", now, at);
+            assert!(at >= last);
+            last = at;
+            ControlFlow::Continue(())
+        });
+        assert_eq!(count, 4);
+        assert_eq!(clock.now(), Duration::from_millis(5));
+    }
+}
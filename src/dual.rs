@@ -0,0 +1,131 @@
+//! 双时间域定时器，游戏时间和现实时间各用一个定时器，暂停时只有现实时间继续滚动
+//! 两个定时器共享同一个关键字空间，取消时无需知道任务属于哪个时间域
+
+use slotmap::SlotMap;
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{RollReport, Timer};
+
+/// 时间域
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Domain {
+    /// 游戏时间，暂停时停止滚动
+    Game,
+    /// 现实时间，始终滚动
+    Real,
+}
+
+/// 双时间域定时器
+pub struct DualTimer<T, const N0: usize, const N: usize, const L: usize> {
+    game: Timer<(TimerKey, T), N0, N, L>,
+    real: Timer<(TimerKey, T), N0, N, L>,
+    /// 对外的关键字到时间域和内部关键字的映射
+    keys: SlotMap<TimerKey, (Domain, TimerKey)>,
+    paused: bool,
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Default for DualTimer<T, N0, N, L> {
+    fn default() -> Self {
+        DualTimer {
+            game: Default::default(),
+            real: Default::default(),
+            keys: SlotMap::with_key(),
+            paused: false,
+        }
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> DualTimer<T, N0, N, L> {
+    /// 获得时间域的定时器，定时元素为 `(对外的关键字, 定时元素)`
+    pub fn timer(&self, domain: Domain) -> &Timer<(TimerKey, T), N0, N, L> {
+        match domain {
+            Domain::Game => &self.game,
+            Domain::Real => &self.real,
+        }
+    }
+    /// 暂停或恢复游戏时间
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+    /// 游戏时间是否暂停
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// 在指定时间域放入一个定时任务，超时时间按该时间域的滚动次数计算
+    pub fn push(&mut self, domain: Domain, timeout: usize, el: T) -> TimerKey {
+        let key = self.keys.insert((domain, TimerKey::default()));
+        let inner = self.timer_mut(domain).push(timeout, (key, el));
+        self.keys[key].1 = inner;
+        key
+    }
+    /// 取消定时任务
+    pub fn cancel(&mut self, key: TimerKey) -> Option<T> {
+        let (domain, inner) = self.keys.remove(key)?;
+        self.timer_mut(domain).cancel(inner).map(|(_, el)| el)
+    }
+    /// 获得定时任务所属的时间域
+    pub fn domain(&self, key: TimerKey) -> Option<Domain> {
+        self.keys.get(key).map(|(domain, _)| *domain)
+    }
+    /// 滚动n次，现实时间总是滚动，游戏时间暂停时不滚动
+    /// * @return `(RollReport, RollReport)` 游戏时间和现实时间的滚动报告
+    pub fn roll_n(&mut self, n: u64) -> (RollReport, RollReport) {
+        let game = if self.paused {
+            RollReport::default()
+        } else {
+            self.game.roll_n(n)
+        };
+        (game, self.real.roll_n(n))
+    }
+    /// 弹出一个已到期的定时任务，先弹出现实时间的任务
+    pub fn pop(&mut self) -> Option<(TimerKey, Domain, T)> {
+        let (domain, (key, el)) = match self.real.pop_ready() {
+            Some((_, r)) => (Domain::Real, r),
+            None => (Domain::Game, self.game.pop_ready()?.1),
+        };
+        self.keys.remove(key);
+        Some((key, domain, el))
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    fn timer_mut(&mut self, domain: Domain) -> &mut Timer<(TimerKey, T), N0, N, L> {
+        match domain {
+            Domain::Game => &mut self.game,
+            Domain::Real => &mut self.real,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dual() {
+        let mut timer: DualTimer<&str, 4, 4, 1> = Default::default();
+        let cooldown = timer.push(Domain::Real, 3, "cooldown");
+        let spawn = timer.push(Domain::Game, 3, "spawn");
+        let buff = timer.push(Domain::Game, 5, "buff");
+        assert_ne!(cooldown, spawn);
+        assert_eq!(timer.domain(buff), Some(Domain::Game));
+        timer.set_paused(true);
+        timer.roll_n(3);
+        assert_eq!(timer.pop(), Some((cooldown, Domain::Real, "cooldown")));
+        assert_eq!(timer.pop(), None);
+        timer.set_paused(false);
+        assert_eq!(timer.cancel(buff), Some("buff"));
+        assert_eq!(timer.cancel(buff), None);
+        timer.roll_n(3);
+        assert_eq!(timer.pop(), Some((spawn, Domain::Game, "spawn")));
+        assert!(timer.is_empty());
+        assert_eq!(timer.timer(Domain::Game).now().get(), 3);
+        assert_eq!(timer.timer(Domain::Real).now().get(), 6);
+    }
+}
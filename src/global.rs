@@ -0,0 +1,87 @@
+//! 进程全局定时器，由定时器服务线程驱动，通过自由函数放入和取消定时回调，无需传递定时器的引用
+
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{callback::Callback, service::TimerService};
+
+/// 全局定时器的配置
+#[derive(Debug, Clone, Copy)]
+pub struct GlobalConfig {
+    /// 滚动一次对应的时长
+    pub tick: Duration,
+}
+
+impl Default for GlobalConfig {
+    fn default() -> Self {
+        GlobalConfig {
+            tick: Duration::from_millis(1),
+        }
+    }
+}
+
+struct Global {
+    service: TimerService<Callback>,
+}
+
+static GLOBAL: OnceLock<Global> = OnceLock::new();
+
+/// 按配置初始化全局定时器，启动服务线程
+/// * @return 已经初始化过时返回false，配置不生效
+pub fn init_global(config: GlobalConfig) -> bool {
+    let mut init = false;
+    global_or_init(config, &mut init);
+    init
+}
+
+/// 经过指定时长后在服务线程调用回调，按调用时的时刻加上时长计算到期时刻，向上取整到滚动次数，不会提前调用
+/// * @tip 未初始化时按默认配置初始化；回调的panic会被捕获，不影响后续回调
+/// * @return 服务已关闭时返回None
+pub fn after<F: FnOnce() + Send + 'static>(dur: Duration, f: F) -> Option<TimerKey> {
+    let global = global_or_init(GlobalConfig::default(), &mut false);
+    global.service.schedule_after(dur, Box::new(f))
+}
+
+/// 取消全局定时器上的回调
+/// * @return 全局定时器未初始化或服务已关闭时返回false
+pub fn cancel(key: TimerKey) -> bool {
+    GLOBAL
+        .get()
+        .is_some_and(|global| global.service.cancel(key))
+}
+
+fn global_or_init(config: GlobalConfig, init: &mut bool) -> &'static Global {
+    GLOBAL.get_or_init(|| {
+        assert!(!config.tick.is_zero(), "tick must be positive");
+        *init = true;
+        Global {
+            service: TimerService::spawn::<64, 64, 3, _>(config.tick, |_, f: Callback| {
+                let _ = catch_unwind(AssertUnwindSafe(f));
+            }),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global() {
+        use std::sync::mpsc::channel;
+        let (tx, rx) = channel();
+        let tx2 = tx.clone();
+        let key = after(Duration::from_millis(20), move || tx2.send(1).unwrap()).unwrap();
+        assert!(!init_global(GlobalConfig::default()));
+        after(Duration::from_millis(5), || panic!("caught")).unwrap();
+        after(Duration::from_millis(10), move || tx.send(0).unwrap()).unwrap();
+        assert!(cancel(key));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(0));
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+}
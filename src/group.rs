@@ -0,0 +1,246 @@
+//! 分组定时器，每个定时任务属于一个分组（如多租户网关中的租户），按分组统计和限制待处理的任务数量
+//! 公平模式下，同一时刻到期的任务按分组轮流弹出，避免一个分组的大量到期任务挤占其他分组
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{tick::Tick, PushError, Timer};
+
+/// 分组定时器
+pub struct GroupTimer<G, T, const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<(G, T), N0, N, L>,
+    /// 各分组待处理的任务数量，数量为0的分组不保留
+    lens: HashMap<G, usize>,
+    /// 各分组的配额，没有配额的分组不限制
+    quotas: HashMap<G, usize>,
+    /// 是否公平弹出
+    fair: bool,
+    /// 各分组每轮弹出的任务数量，默认为1
+    weights: HashMap<G, usize>,
+    /// 公平模式下已从定时器取出、等待轮流弹出的同一时刻到期的任务
+    staged: HashMap<G, VecDeque<(TimerKey, T)>>,
+    /// 轮流弹出的分组顺序，队首为当前分组
+    order: VecDeque<G>,
+    /// 当前分组在本轮已弹出的任务数量
+    served: usize,
+    /// 等待轮流弹出的任务数量
+    staged_len: usize,
+}
+
+impl<G, T, const N0: usize, const N: usize, const L: usize> Default for GroupTimer<G, T, N0, N, L> {
+    fn default() -> Self {
+        GroupTimer {
+            timer: Default::default(),
+            lens: HashMap::new(),
+            quotas: HashMap::new(),
+            fair: false,
+            weights: HashMap::new(),
+            staged: HashMap::new(),
+            order: VecDeque::new(),
+            served: 0,
+            staged_len: 0,
+        }
+    }
+}
+
+impl<G: Hash + Eq + Clone, T, const N0: usize, const N: usize, const L: usize>
+    GroupTimer<G, T, N0, N, L>
+{
+    /// 获得内部的定时器，定时元素为 `(分组, 定时元素)`
+    pub fn timer(&self) -> &Timer<(G, T), N0, N, L> {
+        &self.timer
+    }
+    /// 设置分组待处理任务数量的配额，已超出配额的任务不受影响
+    pub fn set_group_quota(&mut self, group: G, quota: usize) {
+        self.quotas.insert(group, quota);
+    }
+    /// 移除分组的配额
+    pub fn remove_group_quota(&mut self, group: &G) -> Option<usize> {
+        self.quotas.remove(group)
+    }
+    /// 获得分组的配额
+    pub fn group_quota(&self, group: &G) -> Option<usize> {
+        self.quotas.get(group).copied()
+    }
+    /// 获得分组待处理的任务数量
+    pub fn group_len(&self, group: &G) -> usize {
+        self.lens.get(group).copied().unwrap_or(0)
+    }
+    /// 迭代有待处理任务的分组及其任务数量，顺序不确定
+    pub fn group_lens(&self) -> impl Iterator<Item = (&G, usize)> + '_ {
+        self.lens.iter().map(|(g, len)| (g, *len))
+    }
+    /// 设置是否公平弹出，默认关闭
+    /// * @tip 开启后同一时刻到期的任务先全部取出，再按分组轮流弹出，每个分组每轮弹出其权重个任务
+    pub fn set_fair(&mut self, fair: bool) {
+        self.fair = fair;
+    }
+    /// 是否公平弹出
+    pub fn is_fair(&self) -> bool {
+        self.fair
+    }
+    /// 设置分组在公平弹出时每轮弹出的任务数量
+    pub fn set_group_weight(&mut self, group: G, weight: usize) {
+        assert!(weight > 0, "group weight must be positive");
+        self.weights.insert(group, weight);
+    }
+    /// 获得分组在公平弹出时每轮弹出的任务数量
+    pub fn group_weight(&self, group: &G) -> usize {
+        self.weights.get(group).copied().unwrap_or(1)
+    }
+    /// 放入一个定时任务，分组达到配额时返回 `PushError::Quota` 和定时元素
+    pub fn push(&mut self, group: G, timeout: usize, el: T) -> Result<TimerKey, PushError<T>> {
+        let len = self.group_len(&group);
+        if self.group_quota(&group).is_some_and(|quota| len >= quota) {
+            return Err(PushError::Quota(el));
+        }
+        let key = self
+            .timer
+            .try_push(timeout, (group.clone(), el))
+            .map_err(|e| match e {
+                PushError::Shutdown((_, el)) => PushError::Shutdown(el),
+                PushError::Full((_, el)) => PushError::Full(el),
+                PushError::Overflow((_, el)) => PushError::Overflow(el),
+                PushError::TooLarge((_, el)) => PushError::TooLarge(el),
+                PushError::Quota((_, el)) => PushError::Quota(el),
+                PushError::Stale((_, el)) => PushError::Stale(el),
+            })?;
+        self.lens.insert(group, len + 1);
+        Ok(key)
+    }
+    /// 取消定时任务，包括已取出等待轮流弹出的任务
+    pub fn cancel(&mut self, key: TimerKey) -> Option<(G, T)> {
+        let (group, el) = match self.timer.cancel(key) {
+            Some(r) => r,
+            None => self.unstage(key)?,
+        };
+        self.release(&group);
+        Some((group, el))
+    }
+    /// 弹出指定时间内的一个定时任务，公平模式下同一时刻到期的任务按分组轮流弹出
+    pub fn pop(&mut self, now: u64) -> Option<(TimerKey, G, T)> {
+        if self.staged_len == 0 {
+            if !self.fair {
+                let (key, (group, el)) = self.timer.pop_at(Tick(now))?;
+                self.release(&group);
+                return Some((key, group, el));
+            }
+            self.stage(now)?;
+        }
+        let group = self.order.front()?.clone();
+        let queue = self.staged.get_mut(&group)?;
+        let (key, el) = queue.pop_front()?;
+        self.staged_len -= 1;
+        self.served += 1;
+        if queue.is_empty() {
+            self.staged.remove(&group);
+            self.order.pop_front();
+            self.served = 0;
+        } else if self.served >= self.group_weight(&group) {
+            self.order.rotate_left(1);
+            self.served = 0;
+        }
+        self.release(&group);
+        Some((key, group, el))
+    }
+    /// 获得定时任务的数量，包括已取出等待轮流弹出的任务
+    pub fn len(&self) -> usize {
+        self.timer.len() + self.staged_len
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// 取出下一个到期时刻的全部任务，按分组排队
+    fn stage(&mut self, now: u64) -> Option<()> {
+        let first = self.timer.pop_expired(now)?;
+        let mut item = Some((first.key, first.el));
+        while let Some((key, (group, el))) = item {
+            match self.staged.get_mut(&group) {
+                Some(queue) => queue.push_back((key, el)),
+                None => {
+                    self.order.push_back(group.clone());
+                    self.staged.insert(group, VecDeque::from([(key, el)]));
+                }
+            }
+            self.staged_len += 1;
+            item = self.timer.pop_at(Tick(first.deadline));
+        }
+        Some(())
+    }
+    /// 从等待轮流弹出的任务中移除指定任务
+    fn unstage(&mut self, key: TimerKey) -> Option<(G, T)> {
+        let (group, queue) = self
+            .staged
+            .iter_mut()
+            .find(|(_, queue)| queue.iter().any(|(k, _)| *k == key))?;
+        let group = group.clone();
+        let i = queue.iter().position(|(k, _)| *k == key)?;
+        let (_, el) = queue.remove(i)?;
+        self.staged_len -= 1;
+        if queue.is_empty() {
+            self.staged.remove(&group);
+            let i = self.order.iter().position(|g| *g == group)?;
+            self.order.remove(i);
+            if i == 0 {
+                self.served = 0;
+            }
+        }
+        Some((group, el))
+    }
+    fn release(&mut self, group: &G) {
+        if let Some(len) = self.lens.get_mut(group) {
+            *len -= 1;
+            if *len == 0 {
+                self.lens.remove(group);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_quota() {
+        let mut timer: GroupTimer<&str, u32, 4, 4, 1> = Default::default();
+        timer.set_group_quota("a", 2);
+        let k = timer.push("a", 1, 0).unwrap();
+        timer.push("a", 2, 1).unwrap();
+        assert!(matches!(timer.push("a", 3, 2), Err(PushError::Quota(2))));
+        timer.push("b", 1, 3).unwrap();
+        assert_eq!(timer.group_len(&"a"), 2);
+        assert_eq!(timer.cancel(k), Some(("a", 0)));
+        timer.push("a", 3, 4).unwrap();
+        assert_eq!(timer.pop(1).map(|r| (r.1, r.2)), Some(("b", 3)));
+        assert_eq!(timer.group_len(&"b"), 0);
+        assert_eq!(timer.group_lens().count(), 1);
+    }
+
+    #[test]
+    fn test_group_fair() {
+        let mut timer: GroupTimer<&str, u32, 4, 4, 1> = Default::default();
+        for i in 0..4 {
+            timer.push("a", 1, i).unwrap();
+        }
+        let k = timer.push("b", 1, 10).unwrap();
+        timer.push("b", 1, 11).unwrap();
+        timer.push("c", 1, 20).unwrap();
+        timer.push("c", 2, 21).unwrap();
+        timer.set_fair(true);
+        timer.set_group_weight("a", 2);
+        let mut r = vec![timer.pop(2).unwrap().2];
+        assert_eq!(timer.cancel(k), Some(("b", 10)));
+        assert_eq!(timer.len(), 6);
+        while let Some((_, _, el)) = timer.pop(2) {
+            r.push(el);
+        }
+        assert_eq!(r, [0, 1, 11, 20, 2, 3, 21]);
+        assert_eq!(timer.group_lens().count(), 0);
+    }
+}
@@ -0,0 +1,630 @@
+//! 侵入式定时器，链接字段嵌入在定时元素中，轮的链表直接串起元素，没有单独的链表节点
+//! 元素实现 `Linked` 交出内嵌的 `Link`，节点数组只存放元素本身和版本号，链接用u32的位置代替64位的关键字
+//!
+//! 关键字类型可选：默认的 `IntrusiveKey` 为32位位置加32位版本号，任务少于2^24个时可用32位的 `CompactKey`
+//!
+//! 轮默认4层，每层64个槽位，可用 `with_levels` 按层指定槽位数（如256/64/64/32）以匹配超时时间的分布；
+//! 最大定时时间为各层槽位数之积，超出的任务放在溢出桶中，每转完一圈重新检查
+//!
+//! 桶的存储由 `Buckets` 后端决定：默认的 `ListBuckets` 为双向链表；同一时刻大量到期而很少取消时，
+//! `VecBuckets` 每个桶用一个Vec，弹出和降层时顺序访问，取消时按链接中记录的位置交换删除
+
+use std::{marker::PhantomData, mem};
+
+use crate::tick::Tick;
+
+/// 空位置
+const NIL: u32 = u32::MAX;
+/// 默认的各层槽位数
+const LEVELS: [usize; 4] = [64; 4];
+
+/// 嵌入在定时元素中的链接字段，由定时器维护
+/// * @tip 元素在定时器中时不要替换它的Link
+#[derive(Debug, Clone)]
+pub struct Link {
+    /// 桶的后端记录的位置
+    refs: [u32; 2],
+    /// 所在的桶，NIL表示不在定时器中
+    bucket: u32,
+    deadline: u64,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Link {
+            refs: [NIL; 2],
+            bucket: NIL,
+            deadline: 0,
+        }
+    }
+}
+
+impl Link {
+    /// 获得到期时间，不在定时器中时为上次的到期时间
+    pub fn deadline(&self) -> Tick {
+        Tick(self.deadline)
+    }
+    /// 是否在定时器中
+    pub fn is_linked(&self) -> bool {
+        self.bucket != NIL
+    }
+    /// 获得桶的后端记录的两个位置，由后端解释
+    pub fn refs(&self) -> [u32; 2] {
+        self.refs
+    }
+    /// 获得桶的后端记录的两个位置的可变引用，只应由后端修改
+    pub fn refs_mut(&mut self) -> &mut [u32; 2] {
+        &mut self.refs
+    }
+}
+
+/// 内嵌链接字段的定时元素
+pub trait Linked {
+    /// 获得内嵌的链接字段
+    fn link(&self) -> &Link;
+    /// 获得内嵌的链接字段的可变引用
+    fn link_mut(&mut self) -> &mut Link;
+}
+
+/// 按节点位置访问内嵌的链接字段，供桶的后端维护链接
+pub trait LinkTable {
+    /// 获得位置为index的节点的链接字段
+    fn link_mut(&mut self, index: u32) -> &mut Link;
+}
+
+/// 桶的存储后端，轮的每个槽位、溢出桶和就绪桶各是一个桶
+pub trait Buckets {
+    /// 创建n个空桶
+    fn with_buckets(n: usize) -> Self;
+    /// 把任务放入桶
+    fn push<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, index: u32);
+    /// 把任务从所在的桶中移除
+    fn remove<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, index: u32);
+    /// 获得桶中的一个任务，桶为空时返回None
+    fn peek(&self, bucket: u32) -> Option<u32>;
+    /// 清空桶，把其中的任务依次追加到out
+    fn take<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, out: &mut Vec<u32>);
+}
+
+/// 双向链表的桶，链接的两个位置为后一个和前一个任务，按放入顺序弹出
+pub struct ListBuckets {
+    /// 每个桶的头尾位置
+    heads: Vec<(u32, u32)>,
+}
+
+impl Buckets for ListBuckets {
+    fn with_buckets(n: usize) -> Self {
+        ListBuckets {
+            heads: vec![(NIL, NIL); n],
+        }
+    }
+    fn push<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, index: u32) {
+        let tail = self.heads[bucket as usize].1;
+        *links.link_mut(index).refs_mut() = [NIL, tail];
+        match tail {
+            NIL => self.heads[bucket as usize].0 = index,
+            tail => links.link_mut(tail).refs_mut()[0] = index,
+        }
+        self.heads[bucket as usize].1 = index;
+    }
+    fn remove<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, index: u32) {
+        let [next, prev] = links.link_mut(index).refs();
+        match prev {
+            NIL => self.heads[bucket as usize].0 = next,
+            prev => links.link_mut(prev).refs_mut()[0] = next,
+        }
+        match next {
+            NIL => self.heads[bucket as usize].1 = prev,
+            next => links.link_mut(next).refs_mut()[1] = prev,
+        }
+    }
+    fn peek(&self, bucket: u32) -> Option<u32> {
+        Some(self.heads[bucket as usize].0).filter(|index| *index != NIL)
+    }
+    fn take<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, out: &mut Vec<u32>) {
+        let (mut index, _) = mem::replace(&mut self.heads[bucket as usize], (NIL, NIL));
+        while index != NIL {
+            out.push(index);
+            index = links.link_mut(index).refs()[0];
+        }
+    }
+}
+
+/// 每个桶一个Vec，链接的第一个位置为任务在Vec中的下标，取消时与最后一个任务交换后删除
+/// * @tip 同一桶中的任务从后往前弹出，取消会打乱顺序
+pub struct VecBuckets {
+    slots: Vec<Vec<u32>>,
+}
+
+impl Buckets for VecBuckets {
+    fn with_buckets(n: usize) -> Self {
+        VecBuckets {
+            slots: (0..n).map(|_| Vec::new()).collect(),
+        }
+    }
+    fn push<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, index: u32) {
+        let slot = &mut self.slots[bucket as usize];
+        *links.link_mut(index).refs_mut() = [slot.len() as u32, NIL];
+        slot.push(index);
+    }
+    fn remove<L: LinkTable + ?Sized>(&mut self, links: &mut L, bucket: u32, index: u32) {
+        let slot = &mut self.slots[bucket as usize];
+        let pos = links.link_mut(index).refs()[0] as usize;
+        slot.swap_remove(pos);
+        if let Some(&moved) = slot.get(pos) {
+            links.link_mut(moved).refs_mut()[0] = pos as u32;
+        }
+    }
+    fn peek(&self, bucket: u32) -> Option<u32> {
+        self.slots[bucket as usize].last().copied()
+    }
+    fn take<L: LinkTable + ?Sized>(&mut self, _links: &mut L, bucket: u32, out: &mut Vec<u32>) {
+        out.append(&mut self.slots[bucket as usize]);
+    }
+}
+
+/// 侵入式定时器的关键字类型，由节点数组中的位置和版本号组成，位置被重用后旧的关键字失效
+pub trait NodeKey: Copy + Eq {
+    /// 可表示的位置数量
+    const CAPACITY: u32;
+    /// 由位置和版本号构造，版本号超出可表示的范围时截断
+    fn new(index: u32, version: u32) -> Self;
+    /// 获得位置
+    fn index(self) -> u32;
+}
+
+/// 64位的关键字，32位位置加32位版本号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IntrusiveKey {
+    index: u32,
+    version: u32,
+}
+
+impl NodeKey for IntrusiveKey {
+    const CAPACITY: u32 = NIL;
+    fn new(index: u32, version: u32) -> Self {
+        IntrusiveKey { index, version }
+    }
+    fn index(self) -> u32 {
+        self.index
+    }
+}
+
+/// 32位的关键字，低24位为位置，高8位为版本号
+/// * @tip 最多2^24个任务；版本号只有8位，同一位置被重用256次后，仍持有的旧关键字会指向新的任务
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompactKey(u32);
+
+impl NodeKey for CompactKey {
+    const CAPACITY: u32 = 1 << 24;
+    fn new(index: u32, version: u32) -> Self {
+        CompactKey(version << 24 | index)
+    }
+    fn index(self) -> u32 {
+        self.0 & (Self::CAPACITY - 1)
+    }
+}
+
+struct Node<T> {
+    version: u32,
+    el: Option<T>,
+}
+
+impl<T: Linked> LinkTable for [Node<T>] {
+    fn link_mut(&mut self, index: u32) -> &mut Link {
+        self[index as usize]
+            .el
+            .as_mut()
+            .expect("linked node is occupied")
+            .link_mut()
+    }
+}
+
+/// 侵入式定时器，关键字类型为K，桶的后端为B
+pub struct IntrusiveTimer<T: Linked, K: NodeKey = IntrusiveKey, B: Buckets = ListBuckets> {
+    nodes: Vec<Node<T>>,
+    /// 空闲的位置
+    free: Vec<u32>,
+    /// 依次为各层的槽位、溢出桶、就绪桶
+    buckets: B,
+    /// 降层时暂存桶中的任务
+    scratch: Vec<u32>,
+    /// 各层的槽位数
+    sizes: Vec<u64>,
+    /// 各层一个槽位对应的滚动次数，最后一个为最大定时时间
+    spans: Vec<u64>,
+    /// 各层第一个槽位的桶编号，最后一个为溢出桶
+    offsets: Vec<u32>,
+    /// 每层的任务数量，全部为空时可以跳过滚动
+    level_len: Vec<usize>,
+    now: u64,
+    len: usize,
+    _key: PhantomData<K>,
+}
+
+impl<T: Linked, K: NodeKey, B: Buckets> Default for IntrusiveTimer<T, K, B> {
+    fn default() -> Self {
+        IntrusiveTimer::with_capacity(0)
+    }
+}
+
+impl<T: Linked, K: NodeKey, B: Buckets> IntrusiveTimer<T, K, B> {
+    /// 创建定时器，预分配capacity个节点
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut timer = IntrusiveTimer::with_levels(&LEVELS);
+        timer.nodes.reserve(capacity);
+        timer
+    }
+    /// 创建按层指定槽位数的定时器，第0层在前
+    /// * @tip 每层至少2个槽位；各层槽位数之积为最大定时时间，不能超出u64
+    pub fn with_levels(levels: &[usize]) -> Self {
+        assert!(!levels.is_empty(), "at least one level");
+        let mut spans = vec![1u64];
+        let mut offsets = vec![0u32];
+        for &size in levels {
+            assert!(size >= 2, "level size must be at least 2");
+            let span = spans[spans.len() - 1].checked_mul(size as u64);
+            spans.push(span.expect("total span overflows u64"));
+            let offset = u32::try_from(size)
+                .ok()
+                .and_then(|s| offsets[offsets.len() - 1].checked_add(s));
+            offsets.push(offset.filter(|o| *o < NIL - 1).expect("too many slots"));
+        }
+        IntrusiveTimer {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            buckets: B::with_buckets(offsets[levels.len()] as usize + 2),
+            scratch: Vec::new(),
+            sizes: levels.iter().map(|s| *s as u64).collect(),
+            spans,
+            offsets,
+            level_len: vec![0; levels.len()],
+            now: 0,
+            len: 0,
+            _key: PhantomData,
+        }
+    }
+    /// 获得各层的槽位数
+    pub fn level_sizes(&self) -> &[u64] {
+        &self.sizes
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// 获得当前时刻
+    pub fn now(&self) -> Tick {
+        Tick(self.now)
+    }
+    /// 获得轮的最大定时时间，超出的任务放在溢出桶中
+    pub fn max_timeout(&self) -> u64 {
+        self.spans[self.sizes.len()]
+    }
+    /// 放入一个定时任务，超时时间按滚动次数计算
+    pub fn push(&mut self, timeout: usize, el: T) -> K {
+        self.push_at(Tick(self.now.saturating_add(timeout as u64)), el)
+    }
+    /// 放入一个在指定时刻到期的定时任务，已过去的时刻在下次弹出时到期
+    pub fn push_at(&mut self, deadline: Tick, mut el: T) -> K {
+        *el.link_mut() = Link {
+            deadline: deadline.0,
+            ..Default::default()
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index as usize].el = Some(el);
+                index
+            }
+            None => {
+                assert!(self.nodes.len() < K::CAPACITY as usize, "too many timers");
+                self.nodes.push(Node {
+                    version: 0,
+                    el: Some(el),
+                });
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        self.place(index);
+        self.len += 1;
+        K::new(index, self.nodes[index as usize].version)
+    }
+    /// 取消定时任务
+    /// * @return 关键字已失效时返回None
+    pub fn cancel(&mut self, key: K) -> Option<T> {
+        self.get(key)?;
+        self.unlink(key.index());
+        Some(self.release(key.index()))
+    }
+    /// 关键字对应的定时任务是否还在定时器中
+    pub fn contains_key(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+    /// 获得定时元素
+    pub fn get(&self, key: K) -> Option<&T> {
+        let node = self.nodes.get(key.index() as usize)?;
+        if K::new(key.index(), node.version) != key {
+            return None;
+        }
+        node.el.as_ref()
+    }
+    /// 获得定时元素的可变引用
+    /// * @tip 不要替换元素的Link
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        let node = self.nodes.get_mut(key.index() as usize)?;
+        if K::new(key.index(), node.version) != key {
+            return None;
+        }
+        node.el.as_mut()
+    }
+    /// 滚动到now，弹出一个已到期的定时任务，同一时刻到期的任务的弹出顺序不保证
+    pub fn pop_at(&mut self, now: Tick) -> Option<(K, T)> {
+        loop {
+            if let Some(index) = self.buckets.peek(self.ready()) {
+                self.unlink(index);
+                let key = K::new(index, self.nodes[index as usize].version);
+                return Some((key, self.release(index)));
+            }
+            if self.now >= now.0 {
+                return None;
+            }
+            self.skip(now.0);
+            self.roll();
+        }
+    }
+    /// 各层都为空时，下一次有任务到期或降层的时刻是更高一层的槽位边界，直接前进到边界之前
+    fn skip(&mut self, target: u64) {
+        let level = match self.level_len.iter().position(|len| *len > 0) {
+            Some(0) => return,
+            Some(level) => level,
+            None => self.sizes.len(),
+        };
+        let span = self.spans[level];
+        let boundary = (self.now / span + 1).saturating_mul(span);
+        self.now = self.now.max(boundary.min(target) - 1);
+    }
+    /// 滚动一次，在槽位边界上从高到低把任务降到低层，再把第0层当前槽位的任务移到就绪桶
+    fn roll(&mut self) {
+        self.now += 1;
+        let levels = self.sizes.len();
+        if self.now.is_multiple_of(self.spans[levels]) {
+            self.cascade(self.offsets[levels]);
+        }
+        for level in (0..levels).rev() {
+            if self.now.is_multiple_of(self.spans[level]) {
+                self.cascade(self.bucket(level, self.now));
+            }
+        }
+    }
+    /// 到期时间在某层对应的桶
+    fn bucket(&self, level: usize, deadline: u64) -> u32 {
+        let slot = deadline / self.spans[level] % self.sizes[level];
+        self.offsets[level] + slot as u32
+    }
+    /// 桶所在的层，溢出桶和就绪桶不属于任何层
+    fn level_of(&self, bucket: u32) -> Option<usize> {
+        if bucket >= self.offsets[self.sizes.len()] {
+            return None;
+        }
+        Some(self.offsets.partition_point(|o| *o <= bucket) - 1)
+    }
+    fn ready(&self) -> u32 {
+        self.offsets[self.sizes.len()] + 1
+    }
+    /// 重新放置桶中的全部任务
+    fn cascade(&mut self, bucket: u32) {
+        let mut scratch = mem::take(&mut self.scratch);
+        self.buckets.take(&mut self.nodes[..], bucket, &mut scratch);
+        if let Some(level) = self.level_of(bucket) {
+            self.level_len[level] -= scratch.len();
+        }
+        for &index in &scratch {
+            self.place(index);
+        }
+        scratch.clear();
+        self.scratch = scratch;
+    }
+    /// 按到期时间把任务放入桶：已到期的放入就绪桶，否则放入上一层槽位与当前时刻相同的最低层，
+    /// 当前时刻到达该层槽位的起点时降到更低的层
+    fn place(&mut self, index: u32) {
+        let deadline = self.link(index).deadline;
+        let bucket = if deadline <= self.now {
+            self.ready()
+        } else {
+            let now = self.now;
+            match (0..self.sizes.len()).find(|&level| {
+                let span = self.spans[level + 1];
+                deadline / span == now / span
+            }) {
+                Some(level) => {
+                    self.level_len[level] += 1;
+                    self.bucket(level, deadline)
+                }
+                None => self.offsets[self.sizes.len()],
+            }
+        };
+        self.link(index).bucket = bucket;
+        self.buckets.push(&mut self.nodes[..], bucket, index);
+    }
+    /// 把任务从所在的桶中移除
+    fn unlink(&mut self, index: u32) {
+        let bucket = mem::replace(&mut self.link(index).bucket, NIL);
+        self.buckets.remove(&mut self.nodes[..], bucket, index);
+        if let Some(level) = self.level_of(bucket) {
+            self.level_len[level] -= 1;
+        }
+    }
+    /// 取出已从桶中移除的定时元素，位置的版本号加1后放回空闲列表
+    fn release(&mut self, index: u32) -> T {
+        let node = &mut self.nodes[index as usize];
+        node.version = node.version.wrapping_add(1);
+        self.free.push(index);
+        self.len -= 1;
+        node.el.take().expect("linked node is occupied")
+    }
+    fn link(&mut self, index: u32) -> &mut Link {
+        self.nodes[..].link_mut(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::{RngCore, SeedableRng};
+    use std::fmt;
+
+    #[derive(Debug, Default)]
+    struct Job {
+        id: u64,
+        link: Link,
+    }
+
+    impl Linked for Job {
+        fn link(&self) -> &Link {
+            &self.link
+        }
+        fn link_mut(&mut self) -> &mut Link {
+            &mut self.link
+        }
+    }
+
+    fn job(id: u64) -> Job {
+        Job {
+            id,
+            ..Default::default()
+        }
+    }
+
+    /// 大部分任务在轮内，少量超出最大定时时间，随机取消一半
+    fn check_random<K: NodeKey + fmt::Debug, B: Buckets>(levels: &[usize]) {
+        let mut timer: IntrusiveTimer<Job, K, B> = IntrusiveTimer::with_levels(levels);
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(13400);
+        let max = timer.max_timeout();
+        let mut keys = Vec::new();
+        for i in 0..3000u64 {
+            let timeout = match i % 100 {
+                0 => max + rng.next_u64() % (3 * max),
+                _ => rng.next_u64() % max,
+            };
+            keys.push((timer.push(timeout as usize, job(i)), timeout, i));
+        }
+        assert_eq!(timer.len(), 3000);
+        let mut expect = Vec::new();
+        for (j, (key, deadline, i)) in keys.into_iter().enumerate() {
+            assert_eq!(timer.get(key).unwrap().link().deadline(), Tick(deadline));
+            if j % 2 == 0 {
+                let el = timer.cancel(key).unwrap();
+                assert_eq!(el.id, i);
+                assert!(!el.link().is_linked());
+                assert!(!timer.contains_key(key));
+                assert!(timer.cancel(key).is_none());
+            } else {
+                expect.push((deadline, i));
+            }
+        }
+        expect.sort_unstable();
+        let mut popped = Vec::new();
+        while let Some((key, el)) = timer.pop_at(Tick(4 * max)) {
+            assert!(timer.get(key).is_none());
+            assert_eq!(el.link().deadline(), timer.now());
+            popped.push((timer.now().get(), el.id));
+        }
+        popped.sort_unstable();
+        assert_eq!(popped, expect);
+        assert!(timer.is_empty());
+        assert_eq!(timer.now(), Tick(4 * max));
+    }
+
+    #[test]
+    fn test_intrusive() {
+        check_random::<IntrusiveKey, ListBuckets>(&LEVELS);
+    }
+
+    #[test]
+    fn test_compact_key() {
+        check_random::<CompactKey, ListBuckets>(&LEVELS);
+        assert_eq!(mem::size_of::<CompactKey>(), 4);
+        let mut timer: IntrusiveTimer<Job, CompactKey> = Default::default();
+        let a = timer.push(1, job(0));
+        assert_eq!(timer.cancel(a).map(|el| el.id), Some(0));
+        // 同一位置被重用255次内，旧的关键字都失效
+        for i in 1..256 {
+            let key = timer.push(1, job(i));
+            assert_eq!(key.index(), a.index());
+            assert_ne!(key, a);
+            assert!(timer.get(a).is_none());
+            timer.cancel(key).unwrap();
+        }
+        assert_eq!(timer.push(1, job(256)), a);
+    }
+
+    #[test]
+    fn test_level_sizes() {
+        check_random::<IntrusiveKey, ListBuckets>(&[256, 64, 64, 32]);
+        check_random::<IntrusiveKey, ListBuckets>(&[10, 3, 7]);
+        let mut timer: IntrusiveTimer<Job> = IntrusiveTimer::with_levels(&[256, 64, 64, 32]);
+        assert_eq!(timer.level_sizes(), [256, 64, 64, 32]);
+        assert_eq!(timer.max_timeout(), 256 * 64 * 64 * 32);
+        // 跨过第0层的边界后从第1层降层，不会提前或推迟
+        timer.pop_at(Tick(250));
+        for (i, timeout) in [5, 6, 262, 16384 + 7].into_iter().enumerate() {
+            timer.push(timeout, job(i as u64));
+        }
+        for (i, deadline) in [255, 256, 512, 16384 + 257].into_iter().enumerate() {
+            assert_eq!(timer.pop_at(Tick(deadline - 1)).map(|r| r.1.id), None);
+            assert_eq!(timer.pop_at(Tick(deadline)).map(|r| r.1.id), Some(i as u64));
+        }
+    }
+
+    #[test]
+    fn test_vec_buckets() {
+        check_random::<IntrusiveKey, VecBuckets>(&LEVELS);
+        check_random::<CompactKey, VecBuckets>(&[256, 64, 64, 32]);
+        // 同一时刻大量到期，取消中间的任务后按位置交换删除，其余任务都能弹出
+        let mut timer: IntrusiveTimer<Job, IntrusiveKey, VecBuckets> = Default::default();
+        let keys: Vec<_> = (0..100).map(|i| timer.push(70, job(i))).collect();
+        for key in keys.iter().step_by(3) {
+            timer.cancel(*key).unwrap();
+        }
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(timer.contains_key(*key), i % 3 != 0);
+        }
+        let mut popped: Vec<_> = std::iter::from_fn(|| timer.pop_at(Tick(70)))
+            .map(|(key, el)| {
+                assert_eq!(keys[el.id as usize], key);
+                el.id
+            })
+            .collect();
+        popped.sort_unstable();
+        assert_eq!(popped, (0..100).filter(|i| i % 3 != 0).collect::<Vec<_>>());
+        assert!(timer.is_empty());
+    }
+
+    #[test]
+    fn test_intrusive_reuse() {
+        let mut timer: IntrusiveTimer<Job> = IntrusiveTimer::with_capacity(2);
+        let a = timer.push(0, job(0));
+        let b = timer.push(3, job(1));
+        timer.get_mut(b).unwrap().id = 2;
+        assert_eq!(
+            timer.pop_at(Tick(0)).map(|(k, el)| (k, el.id)),
+            Some((a, 0))
+        );
+        // 位置被重用后旧的关键字失效
+        let c = timer.push_at(Tick(0), job(3));
+        assert!(timer.get(a).is_none());
+        assert_eq!(
+            timer.pop_at(Tick(0)).map(|(k, el)| (k, el.id)),
+            Some((c, 3))
+        );
+        assert_eq!(timer.pop_at(Tick(2)).map(|r| r.1.id), None);
+        assert_eq!(
+            timer.pop_at(Tick(5)).map(|(k, el)| (k, el.id)),
+            Some((b, 2))
+        );
+        assert_eq!(timer.now(), Tick(3));
+    }
+}
@@ -0,0 +1,201 @@
+//! 外部标识定时器，按调用方自己的标识（如跨机器传递的请求编号）放入、取消和重新定时
+//! 句柄定时器，放入时分配单调递增、永不复用的 `u64` 句柄，用于审计日志等需要全局唯一编号的场合
+
+use std::{collections::HashMap, hash::Hash};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::Timer;
+
+/// 放入的标识已存在时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Duplicate {
+    /// 取消已有的任务，放入新任务
+    #[default]
+    Replace,
+    /// 保留已有的任务，拒绝新任务
+    Reject,
+}
+
+/// 外部标识定时器，维护标识和关键字的双向映射
+pub struct KeyedTimer<I, T, const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<(I, T), N0, N, L>,
+    keys: HashMap<I, TimerKey>,
+    duplicate: Duplicate,
+}
+
+impl<I, T, const N0: usize, const N: usize, const L: usize> Default for KeyedTimer<I, T, N0, N, L> {
+    fn default() -> Self {
+        KeyedTimer {
+            timer: Default::default(),
+            keys: HashMap::new(),
+            duplicate: Duplicate::Replace,
+        }
+    }
+}
+
+impl<I: Hash + Eq + Clone, T, const N0: usize, const N: usize, const L: usize>
+    KeyedTimer<I, T, N0, N, L>
+{
+    /// 获得内部的定时器，定时元素为 `(标识, 定时元素)`
+    pub fn timer(&self) -> &Timer<(I, T), N0, N, L> {
+        &self.timer
+    }
+    /// 设置标识已存在时的处理方式，默认替换
+    pub fn set_duplicate(&mut self, duplicate: Duplicate) {
+        self.duplicate = duplicate;
+    }
+    /// 获得标识已存在时的处理方式
+    pub fn duplicate(&self) -> Duplicate {
+        self.duplicate
+    }
+    /// 按标识放入一个定时任务
+    /// * @return 成功时返回被替换的定时元素，按 `Duplicate::Reject` 拒绝时返回错误和新的定时元素
+    pub fn push_with_id(&mut self, id: I, timeout: usize, el: T) -> Result<Option<T>, T> {
+        let mut old = None;
+        if let Some(&key) = self.keys.get(&id) {
+            if self.duplicate == Duplicate::Reject {
+                return Err(el);
+            }
+            old = self.timer.cancel(key).map(|(_, el)| el);
+        }
+        let key = self.timer.push(timeout, (id.clone(), el));
+        self.keys.insert(id, key);
+        Ok(old)
+    }
+    /// 按标识取消定时任务
+    pub fn cancel_by_id(&mut self, id: &I) -> Option<T> {
+        let key = self.keys.remove(id)?;
+        self.timer.cancel(key).map(|(_, el)| el)
+    }
+    /// 按标识将定时任务按新的超时时间重新定时
+    /// * @return `bool` 任务是否存在
+    pub fn reset_by_id(&mut self, id: &I, timeout: usize) -> bool {
+        match self.keys.get(id) {
+            Some(&key) => self.timer.reschedule(key, timeout),
+            None => false,
+        }
+    }
+    /// 是否有指定标识的定时任务
+    pub fn contains_id(&self, id: &I) -> bool {
+        self.keys.contains_key(id)
+    }
+    /// 获得标识对应的关键字
+    pub fn key(&self, id: &I) -> Option<TimerKey> {
+        self.keys.get(id).copied()
+    }
+    /// 弹出指定时间内的一个标识和定时元素
+    pub fn pop(&mut self, now: u64) -> Option<(I, T)> {
+        let (id, el) = self.timer.pop(now)?;
+        self.keys.remove(&id);
+        Some((id, el))
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.timer.len()
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.timer.is_empty()
+    }
+}
+
+/// 句柄定时器，句柄从0开始单调递增，关键字被slotmap回收复用后句柄也不会重复
+pub struct HandleTimer<T, const N0: usize, const N: usize, const L: usize> {
+    inner: KeyedTimer<u64, T, N0, N, L>,
+    next: u64,
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Default for HandleTimer<T, N0, N, L> {
+    fn default() -> Self {
+        HandleTimer {
+            inner: Default::default(),
+            next: 0,
+        }
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> HandleTimer<T, N0, N, L> {
+    /// 获得内部的定时器，定时元素为 `(句柄, 定时元素)`
+    pub fn timer(&self) -> &Timer<(u64, T), N0, N, L> {
+        self.inner.timer()
+    }
+    /// 放入一个定时任务
+    /// * @return `u64` 新分配的句柄
+    pub fn push(&mut self, timeout: usize, el: T) -> u64 {
+        let handle = self.next;
+        self.next += 1;
+        let _ = self.inner.push_with_id(handle, timeout, el);
+        handle
+    }
+    /// 按句柄取消定时任务
+    pub fn cancel(&mut self, handle: u64) -> Option<T> {
+        self.inner.cancel_by_id(&handle)
+    }
+    /// 按句柄将定时任务按新的超时时间重新定时
+    /// * @return `bool` 任务是否存在
+    pub fn reset(&mut self, handle: u64, timeout: usize) -> bool {
+        self.inner.reset_by_id(&handle, timeout)
+    }
+    /// 是否有指定句柄的定时任务
+    pub fn contains(&self, handle: u64) -> bool {
+        self.inner.contains_id(&handle)
+    }
+    /// 获得句柄对应的关键字
+    pub fn key(&self, handle: u64) -> Option<TimerKey> {
+        self.inner.key(&handle)
+    }
+    /// 弹出指定时间内的一个句柄和定时元素
+    pub fn pop(&mut self, now: u64) -> Option<(u64, T)> {
+        self.inner.pop(now)
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::Key;
+
+    #[test]
+    fn test_keyed() {
+        let mut timer: KeyedTimer<u64, &str, 4, 4, 1> = Default::default();
+        assert_eq!(timer.push_with_id(7, 5, "a"), Ok(None));
+        assert_eq!(timer.push_with_id(7, 3, "b"), Ok(Some("a")));
+        timer.set_duplicate(Duplicate::Reject);
+        assert_eq!(timer.push_with_id(7, 1, "c"), Err("c"));
+        assert_eq!(timer.push_with_id(8, 1, "d"), Ok(None));
+        assert!(timer.reset_by_id(&8, 10));
+        assert!(!timer.reset_by_id(&9, 10));
+        assert_eq!(timer.pop(5), Some((7, "b")));
+        assert!(!timer.contains_id(&7));
+        assert_eq!(timer.cancel_by_id(&8), Some("d"));
+        assert_eq!(timer.cancel_by_id(&8), None);
+        assert!(timer.is_empty());
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut timer: HandleTimer<u32, 4, 4, 1> = Default::default();
+        let a = timer.push(1, 0);
+        let key = timer.key(a).unwrap();
+        assert_eq!(timer.cancel(a), Some(0));
+        let b = timer.push(1, 1);
+        // 关键字的槽位被复用，句柄不复用
+        let index = |k: TimerKey| k.data().as_ffi() as u32;
+        assert_eq!(timer.key(b).map(index), Some(index(key)));
+        assert_eq!((a, b), (0, 1));
+        assert!(!timer.contains(a));
+        assert_eq!(timer.cancel(a), None);
+        assert!(timer.reset(b, 2));
+        assert_eq!(timer.pop(2), Some((b, 1)));
+        assert!(timer.is_empty());
+    }
+}
@@ -1,21 +1,529 @@
 //! 可撤销的定时器
+//!
+//! 定时元素直接内联在slotmap的节点中，没有装箱或额外的间接访问；小元素（如 `u32` 编号）的节点大小主要由链接字段、
+//! 超时时间、到期时间和放入序号决定，专门的小元素定时器无法再省去间接访问，因此不另外提供。
+//! 内联小元素与装箱元素的对比见 `cargo bench --bench payload`。
+//! 元素自带链接字段时可用 `intrusive::IntrusiveTimer`，轮的链表直接串起元素，省去单独的链表节点。
 
-use std::{cmp::Reverse, fmt};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::VecDeque,
+    fmt, mem,
+    sync::atomic::{AtomicU64, Ordering as AtomicOrdering},
+};
 
 use pi_ext_heap::ExtHeap;
-use pi_slot_deque::{LinkedNode, Slot};
+use overflow::{OverflowStore, Store};
+use pi_slot_deque::{Deque, LinkedNode, Slot};
 use pi_slot_wheel::{Result, TimeoutItem, TimerKey, Wheel};
-use slotmap::{Key};
+use slotmap::{Key, SecondaryMap};
 
+/// 更新计数，minimal特性下不生成代码
+macro_rules! count {
+    ($($t:tt)*) => {
+        #[cfg(not(feature = "minimal"))]
+        {
+            $($t)*
+        }
+    };
+}
+
+pub mod any;
+pub mod callback;
+#[cfg(any(feature = "service", feature = "shared"))]
+pub mod cancel;
+pub mod clock;
+pub mod deliver;
+#[cfg(feature = "async")]
+pub mod delay;
+pub mod driver;
+pub mod dual;
+#[cfg(feature = "global")]
+pub mod global;
+pub mod group;
+pub mod intrusive;
+pub mod keyed;
+mod overflow;
+pub mod queue;
+pub mod recurring;
+#[cfg(feature = "recorder")]
+pub mod recorder;
+pub mod scope;
+#[cfg(feature = "service")]
+pub mod service;
+#[cfg(all(feature = "mio", target_os = "linux"))]
+pub mod source;
+pub mod set;
+#[cfg(feature = "shared")]
+pub mod shared;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tick;
+pub mod watchdog;
+
+/// 自带超时时间的定时元素
+pub trait Timeout {
+    /// 获得超时时间
+    fn timeout(&self) -> usize;
+}
+
+/// 放入定时任务失败的错误，携带未能放入的定时元素
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushError<T> {
+    /// 定时器已关闭
+    Shutdown(T),
+    /// 定时任务数量已达到上限
+    Full(T),
+    /// 超时时间超出定时轮的最大定时时间，且溢出策略为拒绝
+    Overflow(T),
+    /// 超时时间过大，到期时间无法表示
+    TooLarge(T),
+    /// 分组的定时任务数量已达到配额
+    Quota(T),
+    /// 放回的关键字不在最近弹出的关键字中，无法按原关键字放回
+    Stale(T),
+}
+
+impl<T> PushError<T> {
+    /// 取回未能放入的定时元素
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Shutdown(el)
+            | PushError::Full(el)
+            | PushError::Overflow(el)
+            | PushError::TooLarge(el)
+            | PushError::Quota(el)
+            | PushError::Stale(el) => el,
+        }
+    }
+}
+
+impl<T> fmt::Display for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Shutdown(_) => f.write_str("timer is shut down"),
+            PushError::Full(_) => f.write_str("timer is full"),
+            PushError::Overflow(_) => f.write_str("timeout exceeds the wheel"),
+            PushError::TooLarge(_) => f.write_str("timeout is too large"),
+            PushError::Quota(_) => f.write_str("group quota exceeded"),
+            PushError::Stale(_) => f.write_str("key is not a recently popped key"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for PushError<T> {}
+
+/// 关闭定时器的报告
+#[derive(Debug)]
+pub struct ShutdownReport<T> {
+    /// 关闭时已到期的定时任务，按弹出顺序排列
+    pub expired: Vec<(TimerKey, T)>,
+    /// 尚未到期的定时任务，元素为 `(到期时间, 关键字, 定时元素)`，按到期时间顺序排列
+    pub pending: Vec<(u64, TimerKey, T)>,
+}
+
+/// 两个定时器的差异，按关键字对应任务，各列表按关键字排序
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimerDiff {
+    /// 只在左边的任务，元素为 `(关键字, 到期时间)`
+    pub only_left: Vec<(TimerKey, u64)>,
+    /// 只在右边的任务，元素为 `(关键字, 到期时间)`
+    pub only_right: Vec<(TimerKey, u64)>,
+    /// 两边都有但到期时间不同的任务，元素为 `(关键字, 左边的到期时间, 右边的到期时间)`
+    pub deadline_changed: Vec<(TimerKey, u64, u64)>,
+}
+
+impl TimerDiff {
+    /// 是否没有差异
+    pub fn is_empty(&self) -> bool {
+        self.only_left.is_empty() && self.only_right.is_empty() && self.deadline_changed.is_empty()
+    }
+}
+
+impl fmt::Display for TimerDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("no difference");
+        }
+        let lists = [("only in left", &self.only_left), ("only in right", &self.only_right)];
+        for (name, list) in lists {
+            if !list.is_empty() {
+                write!(f, "{}:", name)?;
+                for (key, deadline) in list {
+                    write!(f, " {:?}@{}", key, deadline)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        if !self.deadline_changed.is_empty() {
+            write!(f, "deadline changed:")?;
+            for (key, left, right) in &self.deadline_changed {
+                write!(f, " {:?}@{}->{}", key, left, right)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// 到期弹出的定时任务
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expired<T> {
+    /// 关键字
+    pub key: TimerKey,
+    /// 放入时请求的超时时间
+    pub timeout: usize,
+    /// 到期时间
+    pub deadline: u64,
+    /// 到期时间之后不足一次滚动的余数，由 `push_with_remainder` 放入
+    #[cfg(feature = "subtick")]
+    pub remainder: std::time::Duration,
+    /// 定时元素
+    pub el: T,
+}
+
+/// 批量滚动的报告
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RollReport {
+    /// 移入就绪队列的到期任务数量
+    pub due: usize,
+    /// 从堆级联到轮中的任务数量
+    pub cascaded: u64,
+    /// 是否滚动到底，修正了堆上的任务
+    pub wrapped: bool,
+}
+
+/// 定时器的统计快照，包括全部计数、占用和延迟，开启serde特性时可直接序列化
+#[cfg(not(feature = "minimal"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TimerStats {
+    /// 添加任务数量
+    pub add: u64,
+    /// 到期弹出的任务数量
+    pub expired: u64,
+    /// 未到期就被移除的任务数量
+    pub cancelled: u64,
+    /// 从堆级联到轮中的任务数量
+    pub cascade: u64,
+    /// 在轮或就绪队列上取消的任务数量
+    pub wheel_cancel: u64,
+    /// 在堆上取消的任务数量
+    pub heap_cancel: u64,
+    /// 弹出延迟超过阈值的任务数量
+    pub late: u64,
+    /// 定时任务的数量
+    pub len: usize,
+    /// 堆上的定时任务的数量
+    pub heap_len: usize,
+    /// 就绪队列中的任务数量
+    pub ready_len: usize,
+    /// 上次清零计数以来，同时存在的任务数量的最大值
+    pub max_len: usize,
+    /// 上次清零计数以来，堆上同时存在的任务数量的最大值
+    pub max_heap_len: usize,
+    /// 滚动次数
+    pub roll_count: u64,
+    /// 最近的到期时间落后于滚动次数的次数，未落后或没有任务时为0
+    pub lag: u64,
+}
+
+/// 定时条目，记录到期时间和放入序号
+#[derive(Debug)]
+struct Entry<T> {
+    el: T,
+    /// 放入时请求的超时时间
+    timeout: usize,
+    /// 到期时的滚动次数
+    deadline: u64,
+    /// 放入序号，同一时刻到期的任务按放入序号先进先出
+    seq: u64,
+    /// 用户数据
+    #[cfg(feature = "user_data")]
+    data: u64,
+    /// 到期时间之后不足一次滚动的余数
+    #[cfg(feature = "subtick")]
+    remainder: std::time::Duration,
+}
+
+/// 取消定时任务时的清理函数
+pub type OnCancel<T> = Box<dyn FnOnce(TimerKey, &mut T) + Send>;
+
+/// 定时器的钩子函数
+struct Hooks<T> {
+    /// 定时器销毁时，处理尚未到期的定时任务
+    on_drop: Option<Box<dyn FnMut(TimerKey, T) + Send>>,
+    /// 每个定时任务被取消时的清理函数，到期弹出时丢弃
+    on_cancel: SecondaryMap<TimerKey, OnCancel<T>>,
+    /// 每次滚动后调用，参数为新的滚动次数和本次滚动的报告
+    #[cfg(not(feature = "minimal"))]
+    on_roll: Option<Box<dyn FnMut(u64, RollReport) + Send>>,
+    /// 弹出的任务延迟超过阈值时调用，参数为关键字和延迟的滚动次数
+    #[cfg(not(feature = "minimal"))]
+    on_late: Option<Box<dyn FnMut(TimerKey, u64) + Send>>,
+    /// 堆长度达到水位线时调用，参数为堆长度
+    #[cfg(not(feature = "minimal"))]
+    on_overflow_pressure: Option<Box<dyn FnMut(usize) + Send>>,
+}
+
+// SAFETY: 钩子函数只在 `&mut Timer` 下调用或取出，共享引用无法调用其中的 `FnMut`/`FnOnce`，
+// 因此钩子只需要Send，不要求Sync，也不影响定时器的Sync
+unsafe impl<T> Sync for Hooks<T> {}
+
+impl<T> Default for Hooks<T> {
+    fn default() -> Self {
+        Hooks {
+            on_drop: None,
+            on_cancel: SecondaryMap::new(),
+            #[cfg(not(feature = "minimal"))]
+            on_roll: None,
+            #[cfg(not(feature = "minimal"))]
+            on_late: None,
+            #[cfg(not(feature = "minimal"))]
+            on_overflow_pressure: None,
+        }
+    }
+}
+
+/// 超时时间的分布直方图
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy)]
+pub struct Histogram {
+    /// 按超时时间的二进制位数分桶，第i个桶统计范围为 `[2^(i-1), 2^i)` 的超时时间，第0个桶统计超时时间为0的
+    pub buckets: [u64; usize::BITS as usize + 1],
+    /// 放入首层轮的任务数量
+    pub layer0: u64,
+    /// 放入后面各层轮的任务数量
+    pub layers: u64,
+    /// 放入堆的任务数量
+    pub heap: u64,
+}
+
+#[cfg(feature = "metrics")]
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            buckets: [0; usize::BITS as usize + 1],
+            layer0: 0,
+            layers: 0,
+            heap: 0,
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Histogram {
+    /// 记录一个超时时间
+    fn record(&mut self, timeout: usize, n0: usize, max_time: usize) {
+        self.buckets[(usize::BITS - timeout.leading_zeros()) as usize] += 1;
+        if timeout < n0 {
+            self.layer0 += 1;
+        } else if timeout < max_time {
+            self.layers += 1;
+        } else {
+            self.heap += 1;
+        }
+    }
+}
+
+/// 定时器的计数，minimal特性下除直方图外全部去掉
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    /// 添加任务数量
+    #[cfg(not(feature = "minimal"))]
+    add: u64,
+    /// 到期弹出的任务数量
+    #[cfg(not(feature = "minimal"))]
+    expired: u64,
+    /// 未到期就被移除的任务数量，包括取消和批量取出
+    #[cfg(not(feature = "minimal"))]
+    cancelled: u64,
+    /// 从堆级联到轮中的任务数量
+    #[cfg(not(feature = "minimal"))]
+    cascade: u64,
+    /// 在轮或就绪队列上取消的任务数量，只需修复链表
+    #[cfg(not(feature = "minimal"))]
+    cancel_wheel: u64,
+    /// 在堆或稀疏堆上取消的任务数量，需要调整堆
+    #[cfg(not(feature = "minimal"))]
+    cancel_heap: u64,
+    /// 弹出延迟超过阈值的任务数量
+    #[cfg(not(feature = "minimal"))]
+    late: u64,
+    /// 同时存在的任务数量的最大值
+    #[cfg(not(feature = "minimal"))]
+    max_len: usize,
+    /// 堆上同时存在的任务数量的最大值
+    #[cfg(not(feature = "minimal"))]
+    max_heap_len: usize,
+    /// 超时时间的分布
+    #[cfg(feature = "metrics")]
+    histogram: Histogram,
+}
+
+/// 到期时间对齐合并窗口时的取整方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// 向前取整，不会晚于请求的时间到期，但不会早于当前时刻
+    Floor,
+    /// 向后取整，不会早于请求的时间到期
+    #[default]
+    Ceil,
+    /// 就近取整
+    Nearest,
+}
+
+/// 超时时间超出定时轮的最大定时时间时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// 放入堆中，按请求的时间到期
+    #[default]
+    Heap,
+    /// 截断为定时轮能容纳的最大定时时间，可能早于请求的时间到期，`set_never_early` 开启时不截断
+    Clamp,
+    /// 拒绝放入，`try_push` 返回 `PushError::Overflow`
+    Reject,
+}
+
+/// 定时器的设置
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    /// 到期时间的合并窗口，到期时间按窗口对齐，使相近的任务在同一时刻到期
+    coalesce: u64,
+    /// 到期时间对齐合并窗口时的取整方式
+    rounding: Rounding,
+    /// 超出定时轮的处理策略
+    overflow: Overflow,
+    /// 保证不早于请求的时间到期
+    never_early: bool,
+    /// 定时任务数量的上限
+    max_pending: usize,
+    /// 滚动到底时不修正堆上的任务，改为累加堆的时间基准
+    lazy_restamp: bool,
+    /// 稀疏模式的任务数量阈值，任务数量低于阈值时只用稀疏堆，0表示关闭
+    sparse_threshold: usize,
+    /// 保留可放回的最近弹出的关键字数量
+    requeue_window: usize,
+    /// 弹出延迟的告警阈值
+    #[cfg(not(feature = "minimal"))]
+    late_threshold: u64,
+    /// 堆长度的水位线
+    #[cfg(not(feature = "minimal"))]
+    heap_watermark: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            coalesce: 1,
+            rounding: Rounding::Ceil,
+            overflow: Overflow::Heap,
+            never_early: false,
+            max_pending: usize::MAX,
+            lazy_restamp: false,
+            sparse_threshold: 0,
+            requeue_window: 0,
+            #[cfg(not(feature = "minimal"))]
+            late_threshold: u64::MAX,
+            #[cfg(not(feature = "minimal"))]
+            heap_watermark: usize::MAX,
+        }
+    }
+}
+
+/// 内联存放的溢出任务的容量，超出定时轮的任务先放入内联数组，满了再放入堆
+const INLINE: usize = 4;
+
+/// 内联的溢出任务，元素为 `(绝对时间, 关键字)`，无序存放
+/// * @tip 大多数定时器只有少量超出定时轮的任务，内联存放可以避免为此分配堆内存；
+///   位置标记从 `N0 + N * L` 开始，堆上的任务的位置标记排在其后
+#[derive(Debug, Default, Clone, Copy)]
+struct Inline {
+    len: usize,
+    items: [(usize, TimerKey); INLINE],
+}
+
+/// 最近到期时间的缓存，u64::MAX表示未知
+/// * @tip 放入时取最小值，移除的任务恰好是缓存的到期时间时置为未知，查询时再遍历重建；
+///   用原子量保存，使只读的查询也能回填缓存；钩子函数也不要求Sync，定时器整体保持Sync
+#[derive(Debug)]
+struct NextCache(AtomicU64);
+
+impl Default for NextCache {
+    fn default() -> Self {
+        NextCache(AtomicU64::new(u64::MAX))
+    }
+}
+
+impl NextCache {
+    fn get(&self) -> Option<u64> {
+        let v = self.0.load(AtomicOrdering::Relaxed);
+        (v != u64::MAX).then_some(v)
+    }
+    fn set(&self, deadline: u64) {
+        self.0.store(deadline, AtomicOrdering::Relaxed);
+    }
+    fn clear(&self) {
+        self.set(u64::MAX);
+    }
+    /// 放入到期时间为deadline的任务，empty为放入前是否没有任务
+    fn push(&self, deadline: u64, empty: bool) {
+        if empty || self.get().is_some_and(|v| deadline < v) {
+            self.set(deadline);
+        }
+    }
+    /// 移除到期时间为deadline的任务
+    fn remove(&self, deadline: u64) {
+        if self.get() == Some(deadline) {
+            self.clear();
+        }
+    }
+    /// 修改已有任务的到期时间
+    fn retime(&self, deadline: &mut u64, new: u64) {
+        if *deadline != new {
+            self.remove(*deadline);
+            *deadline = new;
+            self.push(new, false);
+        }
+    }
+}
+
+/// 就绪队列中任务的位置标记，就绪队列中的任务已到期，不在轮或堆上
+const READY: usize = usize::MAX;
+/// 稀疏堆中任务的位置起点，位置为起点加上在稀疏堆中的下标
+const SPARSE: usize = usize::MAX / 2;
+
+/// 按到期时间排序取出定时任务的迭代器，元素为 `(到期时间, 关键字, 定时元素)`
+pub type DrainSorted<T> = std::iter::Map<
+    std::vec::IntoIter<(u64, u64, TimerKey, T)>,
+    fn((u64, u64, TimerKey, T)) -> (u64, TimerKey, T),
+>;
+/// 消耗定时器的迭代器，按到期时间顺序产生 `(关键字, 到期时间, 定时元素)`
+pub type IntoIter<T> =
+    std::iter::Map<DrainSorted<T>, fn((u64, TimerKey, T)) -> (TimerKey, u64, T)>;
 
 /// 可撤销的定时器
+/// * 弹出的任务严格按到期时间非递减排列：滚动经过的任务按到期时间进入就绪队列，当前槽位在滚动后按放入顺序整理，
+///   因此无需额外的排序模式
+/// * 同一时刻到期的任务，按放入的先后顺序弹出，包括从堆和上层轮级联下来的任务
 pub struct Timer<T, const N0: usize, const N: usize, const L: usize> {
-    slot: Slot<TimerKey, TimeoutItem<T>>,
-    wheel: Wheel<T, N0, N, L>, // 定时轮
-    heap: ExtHeap<Reverse<(usize, TimerKey)>>, // 最小堆
-    add_count: usize,
-    remove_count: usize,
+    slot: Slot<TimerKey, TimeoutItem<Entry<T>>>,
+    wheel: Wheel<Entry<T>, N0, N, L>, // 定时轮
+    heap: Store, // 超出定时轮的任务，默认为最小堆
+    inline: Inline, // 内联的溢出任务，满了才放入堆
+    ready: Deque<TimerKey>, // 滚动经过时尚未弹出的到期任务
+    ready_count: usize,
+    sparse: ExtHeap<Reverse<(u64, u64, TimerKey)>>, // 稀疏模式下按 (到期时间, 放入序号) 排列的最小堆
+    heap_base: usize, // 延迟修正时堆上的时间基准，堆上的时间减去基准为相对当前一圈的时间
+    counts: Counts,
     roll_count: u64,
+    seq: u64,
+    hooks: Hooks<T>,
+    shutdown: bool,
+    settings: Settings,
+    sort_buf: Vec<(u64, TimerKey)>,
+    next: NextCache,
+    popped: VecDeque<TimerKey>, // 最近弹出、槽位仍保留的关键字，按弹出顺序排列
 }
 
 impl<T: fmt::Debug, const N0: usize, const N: usize, const L: usize> fmt::Debug
@@ -25,42 +533,782 @@ impl<T: fmt::Debug, const N0: usize, const N: usize, const L: usize> fmt::Debug
         f.debug_struct("Timer")
             .field("wheel", &self.wheel)
             .field("heap", &self.heap)
-            .field("add_count", &self.add_count)
-            .field("remove_count", &self.remove_count)
+            .field("inline", &self.inline)
+            .field("counts", &self.counts)
             .field("roll_count", &self.roll_count)
             .finish()
     }
 }
+
+/// 按逻辑内容比较，即 `(到期时间, 定时元素)` 的多重集合，不比较关键字、滚动次数和内部布局，轮结构可以不同
+impl<
+        T: PartialEq,
+        const N0: usize,
+        const N: usize,
+        const L: usize,
+        const M0: usize,
+        const M: usize,
+        const ML: usize,
+    > PartialEq<Timer<T, M0, M, ML>> for Timer<T, N0, N, L>
+{
+    fn eq(&self, other: &Timer<T, M0, M, ML>) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let mut left: Vec<_> = self
+            .slot
+            .iter()
+            .map(|(_, n)| (n.el.el.deadline, &n.el.el.el))
+            .collect();
+        let mut right: Vec<_> = other
+            .slot
+            .iter()
+            .map(|(_, n)| (n.el.el.deadline, &n.el.el.el))
+            .collect();
+        left.sort_unstable_by_key(|e| e.0);
+        right.sort_unstable_by_key(|e| e.0);
+        // 按到期时间分组，组内逐个匹配，定时元素只要求PartialEq
+        let mut used = vec![false; right.len()];
+        let mut start = 0;
+        while start < left.len() {
+            let deadline = left[start].0;
+            let end = start + left[start..].iter().take_while(|e| e.0 == deadline).count();
+            // 两边都已按到期时间排序且之前的组大小一致，右边同一区间的首尾到期时间相同即整组相同
+            if right[start].0 != deadline || right[end - 1].0 != deadline {
+                return false;
+            }
+            for (_, el) in &left[start..end] {
+                match (start..end).find(|&j| !used[j] && right[j].1 == *el) {
+                    Some(j) => used[j] = true,
+                    None => return false,
+                }
+            }
+            start = end;
+        }
+        true
+    }
+}
+
 impl<T, const N0: usize, const N: usize, const L: usize> Default for Timer<T, N0, N, L> {
     fn default() -> Self {
+        let () = Self::LAYOUT;
         Timer {
             slot: Default::default(),
             wheel: Default::default(),
             heap: Default::default(),
-            add_count: 0,
-            remove_count: 0,
+            inline: Default::default(),
+            ready: Default::default(),
+            ready_count: 0,
+            sparse: Default::default(),
+            heap_base: 0,
+            counts: Default::default(),
             roll_count: 0,
+            seq: 0,
+            hooks: Default::default(),
+            shutdown: false,
+            settings: Default::default(),
+            sort_buf: Vec::new(),
+            next: Default::default(),
+            popped: VecDeque::new(),
         }
     }
 }
 
+impl<T, const N0: usize, const N: usize, const L: usize> Drop for Timer<T, N0, N, L> {
+    fn drop(&mut self) {
+        self.run_on_cancel();
+        if let Some(mut f) = self.hooks.on_drop.take() {
+            for (_, key, el) in self.drain_sorted() {
+                f(key, el);
+            }
+        }
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> IntoIterator for Timer<T, N0, N, L> {
+    type Item = (TimerKey, u64, T);
+    type IntoIter = IntoIter<T>;
+    /// 按到期时间顺序取出全部定时任务，到期时间相同的按放入顺序
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.drain_sorted().map(|(deadline, key, el)| (key, deadline, el))
+    }
+}
+
+impl<T: Timeout, const N0: usize, const N: usize, const L: usize> Extend<T>
+    for Timer<T, N0, N, L>
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for el in iter {
+            self.push_el(el);
+        }
+    }
+}
+
+impl<T: Timeout, const N0: usize, const N: usize, const L: usize> Timer<T, N0, N, L> {
+    /// 按定时元素自带的超时时间放入一个定时任务
+    pub fn push_el(&mut self, el: T) -> TimerKey {
+        self.push(el.timeout(), el)
+    }
+}
+
 impl<T, const N0: usize, const N: usize, const L: usize> Timer<T, N0, N, L> {
+    /// 轮结构的编译期检查，每层至少一个槽
+    /// * @tip 槽数不要求是2的幂，如N0为100；取模和除法的除数都是编译期常量，由编译器优化为乘法和移位
+    const LAYOUT: () = assert!(N0 > 0 && N > 0, "wheel levels must have at least one slot");
+    /// 定时轮能容纳的最大定时时间，超时时间小于它的任务放在轮上，否则放入堆中
+    pub const MAX_TIME: usize = wheel_capacity(N0, N, L);
+    /// 判断超时时间是否能放在轮上，可用于编译期检查业务的最长超时
+    pub const fn fits_wheel(timeout: usize) -> bool {
+        timeout < Self::MAX_TIME
+    }
+    /// 创建从指定滚动次数开始的定时器
+    pub fn with_start(roll_count: u64) -> Self {
+        let mut timer = Self::default();
+        timer.roll_count = roll_count;
+        timer
+    }
+    /// 创建定时器，预先分配能容纳指定数量定时任务的节点存储
+    /// * @tip 容量内放入不再分配节点存储；超出定时轮的任务放入堆、稀疏模式下放入稀疏堆时仍可能分配。
+    ///   尚不支持自定义分配器或arena：节点存储slotmap和堆pi_ext_heap都不接受分配器参数，全部内存来自全局分配器
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut timer = Self::default();
+        timer.slot = Slot::with_capacity_and_key(capacity);
+        timer
+    }
+    /// 预留至少能再容纳指定数量定时任务的节点存储
+    /// * @tip 移除任务后节点会进入slotmap的空闲链表，被后续放入的任务复用，容量够用后放入和取消不再分配内存
+    pub fn reserve(&mut self, additional: usize) {
+        self.slot.reserve(additional);
+    }
+    /// 获得不重新分配内存时能容纳的定时任务数量
+    pub fn capacity(&self) -> usize {
+        self.slot.capacity()
+    }
+    /// 预热内部存储，用默认值填充占位节点，见 `warm_up_with`
+    pub fn warm_up(&mut self, capacity: usize)
+    where
+        T: Default,
+    {
+        self.warm_up_with(capacity, T::default)
+    }
+    /// 预热内部存储，预留并实际写入能容纳指定数量定时任务的节点存储、堆和排序缓冲
+    /// * @tip 占位节点由 `f` 生成，写入后立即移除，节点进入slotmap的空闲链表；
+    ///   启动后第一批放入不再分配内存，也不再触发缺页
+    pub fn warm_up_with<F: FnMut() -> T>(&mut self, capacity: usize, mut f: F) {
+        let additional = capacity.saturating_sub(self.slot.len());
+        self.slot.reserve(additional);
+        let keys: Vec<TimerKey> = (0..additional)
+            .map(|_| {
+                let el = Entry {
+                    el: f(),
+                    timeout: 0,
+                    deadline: 0,
+                    seq: 0,
+                    #[cfg(feature = "user_data")]
+                    data: 0,
+                    #[cfg(feature = "subtick")]
+                    remainder: Default::default(),
+                };
+                self.slot.insert(LinkedNode::new(
+                    TimeoutItem::new(0, el, READY),
+                    TimerKey::null(),
+                    TimerKey::null(),
+                ))
+            })
+            .collect();
+        for key in keys {
+            self.slot.remove(key);
+        }
+        // 排序缓冲写满占位元素后清空，保留已写入的容量
+        self.heap.warm_up(capacity);
+        self.sort_buf.resize(capacity, (0, TimerKey::null()));
+        self.sort_buf.clear();
+    }
+    #[cfg(not(feature = "minimal"))]
     /// 获得添加任务数量
-    pub fn add_count(&self) -> usize {
-        self.add_count
+    pub fn add_count(&self) -> u64 {
+        self.counts.add
     }
-    /// 获得移除任务数量
-    pub fn remove_count(&self) -> usize {
-        self.remove_count
+    #[cfg(not(feature = "minimal"))]
+    /// 获得移除任务数量，为到期数量与取消数量之和
+    pub fn remove_count(&self) -> u64 {
+        self.counts.expired + self.counts.cancelled
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得到期弹出的任务数量
+    pub fn expired_count(&self) -> u64 {
+        self.counts.expired
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得未到期就被移除的任务数量，包括取消和批量取出
+    pub fn cancelled_count(&self) -> u64 {
+        self.counts.cancelled
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得从堆级联到轮中的任务数量
+    pub fn cascade_count(&self) -> u64 {
+        self.counts.cascade
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得在轮或就绪队列上取消的任务数量，这类取消只需修复链表
+    pub fn wheel_cancel_count(&self) -> u64 {
+        self.counts.cancel_wheel
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得在堆上取消的任务数量，这类取消需要调整堆，占比高时可以考虑增大轮
+    pub fn heap_cancel_count(&self) -> u64 {
+        self.counts.cancel_heap
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得弹出延迟超过阈值的任务数量
+    pub fn late_count(&self) -> u64 {
+        self.counts.late
+    }
+    /// 将全部计数清零，用于按统计周期上报，滚动次数不受影响
+    pub fn reset_counts(&mut self) {
+        self.counts = Counts::default();
+        count! {
+            self.counts.max_len = self.slot.len();
+            self.counts.max_heap_len = self.heap_len();
+        }
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得上次清零计数以来，同时存在的任务数量的最大值
+    pub fn max_len(&self) -> usize {
+        self.counts.max_len
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得上次清零计数以来，堆上同时存在的任务数量的最大值
+    pub fn max_heap_len(&self) -> usize {
+        self.counts.max_heap_len
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得统计快照，超时时间的分布直方图另见 `timeout_histogram`
+    /// * @tip 计算延迟需要遍历全部定时任务
+    pub fn stats(&self) -> TimerStats {
+        let c = &self.counts;
+        TimerStats {
+            add: c.add,
+            expired: c.expired,
+            cancelled: c.cancelled,
+            cascade: c.cascade,
+            wheel_cancel: c.cancel_wheel,
+            heap_cancel: c.cancel_heap,
+            late: c.late,
+            len: self.slot.len(),
+            heap_len: self.heap_len(),
+            ready_len: self.ready_count,
+            max_len: c.max_len,
+            max_heap_len: c.max_heap_len,
+            roll_count: self.roll_count,
+            lag: self
+                .earliest()
+                .map_or(0, |d| self.roll_count.saturating_sub(d)),
+        }
+    }
+    /// 获得上次清零计数以来，放入的超时时间的分布直方图
+    #[cfg(feature = "metrics")]
+    pub fn timeout_histogram(&self) -> &Histogram {
+        &self.counts.histogram
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.slot.len()
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.slot.is_empty()
+    }
+    /// 获得堆上的定时任务的数量，包括内联存放的溢出任务
+    pub fn heap_len(&self) -> usize {
+        self.heap.len() + self.inline.len
     }
     /// 获得滚动次数
+    #[deprecated(note = "use `now`")]
     pub fn roll_count(&self) -> u64 {
         self.roll_count
     }
+    /// 设置滚动次数，用于从快照恢复或与外部的时间起点对齐，只能在没有定时任务时设置
+    /// * @return 有定时任务时不设置，返回false
+    pub fn set_roll_count(&mut self, roll_count: u64) -> bool {
+        if !self.slot.is_empty() {
+            return false;
+        }
+        self.roll_count = roll_count;
+        true
+    }
+    /// 设置定时器销毁时的钩子函数，按到期时间顺序处理尚未到期的定时任务，避免任务被静默丢弃
+    pub fn set_on_drop<F: FnMut(TimerKey, T) + Send + 'static>(&mut self, f: F) {
+        self.hooks.on_drop = Some(Box::new(f));
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 设置每次滚动后调用的钩子函数，参数为新的滚动次数和本次滚动的报告，可用于驱动其他按帧执行的逻辑
+    /// * @tip 稀疏模式下直接跳到到期时间，不逐次滚动，不会调用
+    pub fn set_on_roll<F: FnMut(u64, RollReport) + Send + 'static>(&mut self, f: F) {
+        self.hooks.on_roll = Some(Box::new(f));
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 设置弹出延迟的告警阈值，默认关闭
+    /// * @tip 延迟为弹出时的时间减去到期时间，超过阈值时增加 `late_count` 并调用 `set_on_late` 设置的钩子函数
+    pub fn set_late_threshold(&mut self, threshold: u64) {
+        self.settings.late_threshold = threshold;
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得弹出延迟的告警阈值
+    pub fn late_threshold(&self) -> u64 {
+        self.settings.late_threshold
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 设置弹出延迟超过阈值时的钩子函数，参数为关键字和延迟的滚动次数
+    pub fn set_on_late<F: FnMut(TimerKey, u64) + Send + 'static>(&mut self, f: F) {
+        self.hooks.on_late = Some(Box::new(f));
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 设置堆长度的水位线，默认关闭
+    /// * @tip 堆上的任务在轮滚到底时集中下降，堆过长会造成滚动的尖峰；
+    ///   堆长度增长到水位线时调用 `set_on_overflow_pressure` 设置的钩子函数，降到水位线以下后再次增长到水位线时会再次调用
+    pub fn set_heap_watermark(&mut self, watermark: usize) {
+        self.settings.heap_watermark = watermark;
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 获得堆长度的水位线
+    pub fn heap_watermark(&self) -> usize {
+        self.settings.heap_watermark
+    }
+    #[cfg(not(feature = "minimal"))]
+    /// 设置堆长度达到水位线时的钩子函数，参数为堆长度
+    pub fn set_on_overflow_pressure<F: FnMut(usize) + Send + 'static>(&mut self, f: F) {
+        self.hooks.on_overflow_pressure = Some(Box::new(f));
+    }
+    /// 放入一个定时任务，并设置取消时的清理函数
+    /// * @tip 清理函数在 `cancel`、`clear` 和定时器销毁时调用，任务到期弹出或被批量取出时丢弃
+    pub fn push_with_cancel<F>(&mut self, timeout: usize, el: T, f: F) -> TimerKey
+    where
+        F: FnOnce(TimerKey, &mut T) + Send + 'static,
+    {
+        let key = self.push(timeout, el);
+        self.hooks.on_cancel.insert(key, Box::new(f));
+        key
+    }
+    /// 设置定时任务取消时的清理函数，替换已有的清理函数
+    /// * @return `bool` 任务是否存在
+    pub fn set_on_cancel<F>(&mut self, key: TimerKey, f: F) -> bool
+    where
+        F: FnOnce(TimerKey, &mut T) + Send + 'static,
+    {
+        if !self.slot.contains_key(key) {
+            return false;
+        }
+        self.hooks.on_cancel.insert(key, Box::new(f));
+        true
+    }
+    /// 取消全部定时任务，调用各自的清理函数
+    pub fn clear(&mut self) {
+        self.run_on_cancel();
+        count!(self.counts.cancelled += self.slot.len() as u64);
+        self.slot.clear();
+        self.reset();
+    }
+    /// 调用全部尚未调用的清理函数
+    fn run_on_cancel(&mut self) {
+        if self.hooks.on_cancel.is_empty() {
+            return;
+        }
+        for (key, f) in mem::take(&mut self.hooks.on_cancel) {
+            if let Some(node) = self.slot.get_mut(key) {
+                f(key, &mut node.el.el.el);
+            }
+        }
+    }
+    /// 获得定时轮能容纳的最大定时时间，超过的定时任务放入堆中，同 `MAX_TIME`
+    pub fn max_time(&self) -> usize {
+        Self::MAX_TIME
+    }
+    /// 获得定时轮的层数，包括首层轮
+    pub fn levels(&self) -> usize {
+        L + 1
+    }
+    /// 获得每层轮的槽数量
+    /// * @return `(usize, usize)` 首层轮的槽数量和后面每层轮的槽数量
+    pub fn slots_per_level(&self) -> (usize, usize) {
+        (N0, N)
+    }
+    /// 获得定时轮各层当前指向的槽位
+    /// * @return `(usize, [usize; L])` 首层轮的槽位和后面每层轮的槽位
+    pub fn cursors(&self) -> (usize, [usize; L]) {
+        let pos = self.wheel.roll_count();
+        let mut indexs = [0; L];
+        for (i, index) in indexs.iter_mut().enumerate() {
+            *index = pos / (N0 * N.pow(i as u32)) % N;
+        }
+        (pos % N0, indexs)
+    }
+    /// 生成定时轮结构的Graphviz描述，列出各层有任务的槽位及任务数量、就绪队列、稀疏堆和堆的任务数量
+    /// * @tip 每层只列出有任务的槽位，当前指向的槽位以 `*` 标记，边表示任务逐层下降的方向
+    pub fn dump_dot(&self) -> String {
+        use std::fmt::Write;
+        let mut counts = vec![0usize; N0 + N * L];
+        let (mut ready, mut sparse, mut heap) = (0, 0, 0);
+        for (_, node) in self.slot.iter() {
+            match node.el.index {
+                READY => ready += 1,
+                i if i >= SPARSE => sparse += 1,
+                i if i < N0 + N * L => counts[i] += 1,
+                _ => heap += 1,
+            }
+        }
+        let (cur0, curs) = self.cursors();
+        let mut s = String::new();
+        let _ = writeln!(s, "digraph timer {{");
+        let _ = writeln!(s, "    rankdir=LR;");
+        let _ = writeln!(s, "    node [shape=record];");
+        for layer in 0..=L {
+            let (start, len, cur, span) = if layer == 0 {
+                (0, N0, cur0, 1)
+            } else {
+                (N0 + N * (layer - 1), N, curs[layer - 1], N0 * N.pow(layer as u32 - 1))
+            };
+            let _ = write!(s, "    layer{} [label=\"layer{} x{}", layer, layer, span);
+            for i in 0..len {
+                let n = counts[start + i];
+                if n > 0 || i == cur {
+                    let mark = if i == cur { "*" } else { "" };
+                    let _ = write!(s, "|<s{}> {}{}: {}", i, mark, i, n);
+                }
+            }
+            let _ = writeln!(s, "\"];");
+        }
+        let _ = writeln!(s, "    ready [label=\"ready: {}\"];", ready);
+        let _ = writeln!(s, "    sparse [label=\"sparse: {}\"];", sparse);
+        let _ = writeln!(s, "    heap [label=\"heap: {}\"];", heap);
+        let _ = write!(s, "    heap");
+        for layer in (0..=L).rev() {
+            let _ = write!(s, " -> layer{}", layer);
+        }
+        let _ = writeln!(s, " -> ready;");
+        let _ = writeln!(s, "    sparse -> ready;");
+        let _ = writeln!(s, "}}");
+        s
+    }
+    /// 生成最近到期的limit个定时任务的表格，按到期时间排序，列出到期时间、剩余滚动次数、关键字、位置和定时元素
+    /// * @tip 位置为 `L层/槽位`、`ready`、`sparse` 或 `heap`；需要遍历并排序全部定时任务，只用于调试
+    pub fn report(&self, limit: usize) -> String
+    where
+        T: fmt::Debug,
+    {
+        use std::fmt::Write;
+        let mut entries: Vec<_> = self.slot.iter().collect();
+        entries.sort_unstable_by_key(|(_, node)| (node.el.el.deadline, node.el.el.seq));
+        let mut s = String::new();
+        let _ = writeln!(
+            s,
+            "{:>10} {:>10} {:<16} {:<8} payload",
+            "deadline", "remaining", "key", "location"
+        );
+        for (key, node) in entries.into_iter().take(limit) {
+            let entry = &node.el.el;
+            let location = match node.el.index {
+                READY => "ready".to_string(),
+                i if i >= SPARSE => "sparse".to_string(),
+                i if i < N0 => format!("L0/{}", i),
+                i if i < N0 + N * L => format!("L{}/{}", (i - N0) / N + 1, (i - N0) % N),
+                _ => "heap".to_string(),
+            };
+            let _ = writeln!(
+                s,
+                "{:>10} {:>10} {:<16} {:<8} {:?}",
+                entry.deadline,
+                entry.deadline.saturating_sub(self.roll_count),
+                format!("{:?}", key),
+                location,
+                entry.el
+            );
+        }
+        s
+    }
+    /// 按关键字比较两个定时器中的任务，报告只在一边的任务和到期时间不同的任务，用于测试中比较回放后的定时器
+    /// * @tip 只比较关键字和到期时间，不比较定时元素和内部布局，两个定时器的轮结构可以不同
+    pub fn diff<const M0: usize, const M: usize, const ML: usize>(
+        &self,
+        other: &Timer<T, M0, M, ML>,
+    ) -> TimerDiff {
+        let mut diff = TimerDiff::default();
+        for (key, node) in self.slot.iter() {
+            let deadline = node.el.el.deadline;
+            match other.slot.get(key) {
+                Some(o) if o.el.el.deadline != deadline => {
+                    diff.deadline_changed.push((key, deadline, o.el.el.deadline))
+                }
+                Some(_) => (),
+                None => diff.only_left.push((key, deadline)),
+            }
+        }
+        for (key, node) in other.slot.iter() {
+            if !self.slot.contains_key(key) {
+                diff.only_right.push((key, node.el.el.deadline));
+            }
+        }
+        diff.only_left.sort_unstable();
+        diff.only_right.sort_unstable();
+        diff.deadline_changed.sort_unstable();
+        diff
+    }
+    /// 迭代全部定时任务的关键字，包括轮上和堆上的任务，顺序不确定
+    pub fn keys(&self) -> impl Iterator<Item = TimerKey> + '_ {
+        self.slot.keys()
+    }
+    /// 迭代全部定时任务的定时元素，包括轮上和堆上的任务，顺序不确定
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.slot.values().map(|node| &node.el.el.el)
+    }
     /// 放入一个定时任务
+    /// * @tip 超时时间超出 `max_timeout` 时按 `max_timeout` 放入；定时器关闭后不再放入，定时元素交给销毁时的钩子函数或直接丢弃，返回空关键字；
+    ///   需要取回定时元素时使用 `try_push`
     pub fn push(&mut self, timeout: usize, el: T) -> TimerKey {
-        self.add_count += 1;
-        match self.wheel.push(timeout, el, &mut self.slot) {
+        self.push_with_rounding(timeout, el, self.settings.rounding)
+    }
+    /// 以指定的取整方式放入一个定时任务，取整方式只在设置了合并窗口时生效
+    /// * @tip 定时器关闭后同 `push`
+    pub fn push_with_rounding(&mut self, timeout: usize, el: T, rounding: Rounding) -> TimerKey {
+        if self.shutdown {
+            return self.refuse(TimerKey::null(), el);
+        }
+        let adjusted = self.adjust(timeout, rounding);
+        let el = self.entry(timeout, adjusted, el);
+        self.insert(adjusted, el)
+    }
+    /// 尝试放入一个定时任务，定时器已关闭、超时时间过大、任务数量达到上限或按溢出策略拒绝时，返回错误和定时元素
+    pub fn try_push(
+        &mut self,
+        timeout: usize,
+        el: T,
+    ) -> std::result::Result<TimerKey, PushError<T>> {
+        match self.check_push(timeout) {
+            Ok(()) => Ok(self.push(timeout, el)),
+            Err(e) => Err(e(el)),
+        }
+    }
+    /// 检查能否放入指定超时时间的任务，不能时返回对应的错误构造函数
+    fn check_push(&self, timeout: usize) -> std::result::Result<(), fn(T) -> PushError<T>> {
+        if self.shutdown {
+            return Err(PushError::Shutdown);
+        }
+        if timeout > self.max_timeout() {
+            return Err(PushError::TooLarge);
+        }
+        if self.slot.len() >= self.settings.max_pending {
+            return Err(PushError::Full);
+        }
+        if self.settings.overflow == Overflow::Reject
+            && self.adjust(timeout, self.settings.rounding) >= self.wheel.max_time()
+        {
+            return Err(PushError::Overflow);
+        }
+        Ok(())
+    }
+    /// 放入一个定时任务，任务数量达到上限时，先驱逐到期时间最晚的任务
+    /// * @tip 需要遍历全部定时任务找出驱逐对象
+    /// * @return 新任务的关键字，和被驱逐的关键字及定时元素
+    pub fn push_evict(&mut self, timeout: usize, el: T) -> (TimerKey, Option<(TimerKey, T)>) {
+        self.push_evict_with(timeout, el, |timer| {
+            timer
+                .slot
+                .iter()
+                .max_by_key(|(_, node)| (node.el.el.deadline, node.el.el.seq))
+                .map(|(key, _)| key)
+        })
+    }
+    /// 放入一个定时任务，任务数量达到上限时，先驱逐由选择函数指定的任务
+    /// * @return 新任务的关键字，和被驱逐的关键字及定时元素
+    pub fn push_evict_with<F>(
+        &mut self,
+        timeout: usize,
+        el: T,
+        select: F,
+    ) -> (TimerKey, Option<(TimerKey, T)>)
+    where
+        F: FnOnce(&Self) -> Option<TimerKey>,
+    {
+        let mut evicted = None;
+        if self.slot.len() >= self.settings.max_pending {
+            if let Some(key) = select(self) {
+                evicted = self.cancel(key).map(|el| (key, el));
+            }
+        }
+        (self.push(timeout, el), evicted)
+    }
+    /// 设置定时任务数量的上限，由 `try_push` 和 `push_evict` 检查，`push` 不受限制
+    pub fn set_max_pending(&mut self, max: usize) {
+        self.settings.max_pending = max;
+    }
+    /// 获得定时任务数量的上限
+    pub fn max_pending(&self) -> usize {
+        self.settings.max_pending
+    }
+    /// 放入一个带用户数据的定时任务，用户数据可以只通过关键字读取
+    /// * @tip 定时器关闭后同 `push`
+    #[cfg(feature = "user_data")]
+    pub fn push_with_data(&mut self, timeout: usize, el: T, data: u64) -> TimerKey {
+        if self.shutdown {
+            return self.refuse(TimerKey::null(), el);
+        }
+        let adjusted = self.adjust(timeout, self.settings.rounding);
+        let mut el = self.entry(timeout, adjusted, el);
+        el.data = data;
+        self.insert(adjusted, el)
+    }
+    /// 获得定时任务的用户数据
+    #[cfg(feature = "user_data")]
+    pub fn data(&self, key: TimerKey) -> Option<u64> {
+        self.slot.get(key).map(|node| node.el.el.data)
+    }
+    /// 放入一个带余数的定时任务，余数为到期时间之后不足一次滚动的时长，弹出时由 `pop_expired` 返回
+    /// * @tip 定时器只按滚动次数到期，余数由驱动在到期后自行等待，如短暂自旋或睡眠；定时器关闭后同 `push`
+    #[cfg(feature = "subtick")]
+    pub fn push_with_remainder(
+        &mut self,
+        timeout: usize,
+        el: T,
+        remainder: std::time::Duration,
+    ) -> TimerKey {
+        if self.shutdown {
+            return self.refuse(TimerKey::null(), el);
+        }
+        let adjusted = self.adjust(timeout, self.settings.rounding);
+        let mut el = self.entry(timeout, adjusted, el);
+        el.remainder = remainder;
+        self.insert(adjusted, el)
+    }
+    /// 获得定时任务的余数
+    #[cfg(feature = "subtick")]
+    pub fn remainder(&self, key: TimerKey) -> Option<std::time::Duration> {
+        self.slot.get(key).map(|node| node.el.el.remainder)
+    }
+    /// 设置是否延迟修正堆上的任务，默认关闭
+    /// * @tip 开启后滚动到底时只累加堆的时间基准，不再遍历整个堆，消除大量长定时任务时的滚动尖峰
+    pub fn set_lazy_restamp(&mut self, lazy: bool) {
+        if !lazy && self.heap_base > 0 {
+            self.restamp(self.heap_base);
+            self.heap_base = 0;
+        }
+        self.settings.lazy_restamp = lazy;
+    }
+    /// 是否延迟修正堆上的任务
+    pub fn lazy_restamp(&self) -> bool {
+        self.settings.lazy_restamp
+    }
+    /// 设置到期时间的合并窗口，到期时间按取整方式对齐到窗口的整数倍，用精度换取更少的唤醒和级联，默认为1即不合并
+    pub fn set_coalesce(&mut self, window: u64) {
+        self.settings.coalesce = window.max(1);
+    }
+    /// 获得到期时间的合并窗口
+    pub fn coalesce(&self) -> u64 {
+        self.settings.coalesce
+    }
+    /// 设置到期时间对齐合并窗口时的取整方式，默认向后取整
+    pub fn set_rounding(&mut self, rounding: Rounding) {
+        self.settings.rounding = rounding;
+    }
+    /// 获得到期时间对齐合并窗口时的取整方式
+    pub fn rounding(&self) -> Rounding {
+        self.settings.rounding
+    }
+    /// 设置超时时间超出定时轮的最大定时时间时的处理策略，默认放入堆中
+    /// * @tip 拒绝策略由 `try_push` 检查，`push` 不受限制，仍放入堆中
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.settings.overflow = overflow;
+    }
+    /// 获得超出定时轮的处理策略
+    pub fn overflow(&self) -> Overflow {
+        self.settings.overflow
+    }
+    /// 设置是否保证不早于请求的时间到期，默认关闭
+    /// * @tip 开启后合并窗口总是向后取整，忽略设置和放入时指定的取整方式，截断策略也不再截断，超出定时轮的任务放入堆中
+    pub fn set_never_early(&mut self, never_early: bool) {
+        self.settings.never_early = never_early;
+    }
+    /// 是否保证不早于请求的时间到期
+    pub fn never_early(&self) -> bool {
+        self.settings.never_early
+    }
+    /// 按合并窗口和取整方式调整超时时间，截断策略下不超出定时轮
+    /// * @tip 超出 `max_timeout` 的超时时间先截断，保证到期时间和堆上的时间不溢出
+    fn adjust(&self, timeout: usize, rounding: Rounding) -> usize {
+        let timeout = timeout.min(self.max_timeout());
+        let window = self.settings.coalesce;
+        let rounding = if self.settings.never_early {
+            Rounding::Ceil
+        } else {
+            rounding
+        };
+        let timeout = if window == 1 {
+            timeout
+        } else {
+            let deadline = self.roll_count + timeout as u64;
+            let deadline = match rounding {
+                Rounding::Floor => deadline / window * window,
+                Rounding::Ceil => deadline.div_ceil(window) * window,
+                Rounding::Nearest => (deadline + window / 2) / window * window,
+            };
+            deadline.saturating_sub(self.roll_count) as usize
+        };
+        if self.settings.overflow == Overflow::Clamp && !self.settings.never_early {
+            timeout.min(self.wheel.max_time() - 1)
+        } else {
+            timeout
+        }
+    }
+    /// 创建定时条目，记录请求的超时时间，按调整后的超时时间计算到期时间
+    fn entry(&mut self, timeout: usize, adjusted: usize, el: T) -> Entry<T> {
+        count!(self.counts.add += 1);
+        self.seq += 1;
+        Entry {
+            el,
+            timeout,
+            deadline: self.roll_count + adjusted as u64,
+            seq: self.seq,
+            #[cfg(feature = "user_data")]
+            data: 0,
+            #[cfg(feature = "subtick")]
+            remainder: Default::default(),
+        }
+    }
+    /// 定时器关闭后拒绝放入，定时元素交给销毁时的钩子函数，没有钩子函数时直接丢弃
+    /// * @return 空关键字
+    fn refuse(&mut self, key: TimerKey, el: T) -> TimerKey {
+        if let Some(f) = self.hooks.on_drop.as_mut() {
+            f(key, el);
+        }
+        TimerKey::null()
+    }
+    /// 将定时条目放入轮中，超出轮的最大定时时间则放入堆中，超时时间为0的直接放入就绪队列
+    fn insert(&mut self, timeout: usize, el: Entry<T>) -> TimerKey {
+        self.next.push(el.deadline, self.slot.is_empty());
+        #[cfg(feature = "metrics")]
+        self.counts
+            .histogram
+            .record(timeout, N0, self.wheel.max_time());
+        if timeout == 0 {
+            // 当前槽位的任务都已到期，先移到就绪队列，保持放入顺序
+            self.take_cur();
+            let key = self
+                .ready
+                .push_back(TimeoutItem::new(0, el, READY), &mut self.slot);
+            self.ready_count += 1;
+            count!(self.counts.max_len = self.counts.max_len.max(self.slot.len()));
+            return key;
+        }
+        if self.use_sparse(0) {
+            let key = self.slot.insert(LinkedNode::new(
+                TimeoutItem::new(timeout, el, SPARSE),
+                TimerKey::null(),
+                TimerKey::null(),
+            ));
+            self.place_sparse(key, timeout);
+            count!(self.counts.max_len = self.counts.max_len.max(self.slot.len()));
+            return key;
+        }
+        let key = match self.wheel.push(timeout, el, &mut self.slot) {
             Result::Ok(key) => key,
             Result::Overflow(timeout, el) => {
                 // 将定时任务放入slot中
@@ -70,49 +1318,275 @@ impl<T, const N0: usize, const N: usize, const L: usize> Timer<T, N0, N, L> {
                     TimerKey::null(),
                 ));
                 // 将绝对时间和键放入堆中
-                let loc = self.heap.push(
-                    Reverse((timeout, key)),
-                    &mut self.slot,
-                    set_index::<T, N0, N, L>,
-                );
-                // 修正所在的堆位置
-                // unsafe {
-                //     self.slot.get_unchecked_mut(key).el.index += loc;
-                // }
+                self.push_overflow(timeout + self.heap_base, key);
                 key
             }
+        };
+        count!(self.counts.max_len = self.counts.max_len.max(self.slot.len()));
+        key
+    }
+    /// 按绝对时间放入一个定时任务，时间已过的任务在当前时刻到期
+    /// * @tip 定时器关闭后同 `push`，需要取回定时元素时使用 `try_push_time`
+    #[deprecated(note = "use `push_at`")]
+    pub fn push_time(&mut self, time: u64, el: T) -> TimerKey {
+        self.push(time.saturating_sub(self.roll_count) as usize, el)
+    }
+    /// 尝试按绝对时间放入一个定时任务，超时时间无法用 `usize` 表示时返回 `PushError::TooLarge`，其余同 `try_push`
+    pub fn try_push_time(
+        &mut self,
+        time: u64,
+        el: T,
+    ) -> std::result::Result<TimerKey, PushError<T>> {
+        match usize::try_from(time.saturating_sub(self.roll_count)) {
+            Ok(timeout) => self.try_push(timeout, el),
+            Err(_) => Err(PushError::TooLarge(el)),
         }
     }
+    /// 获得当前能放入的最大超时时间，超过时到期时间或堆上的时间会溢出
+    /// * @tip 延迟修正堆上的任务时，堆的时间基准会逐圈增长，最大超时时间随之减小
+    pub fn max_timeout(&self) -> usize {
+        // 合并窗口会把超时时间向后取整，预留一个窗口
+        let window = self.settings.coalesce;
+        let by_heap = (usize::MAX - self.wheel.max_time() - self.heap_base)
+            .saturating_sub(usize::try_from(window).unwrap_or(usize::MAX));
+        let by_deadline = (u64::MAX - self.roll_count).saturating_sub(window);
+        by_heap.min(usize::try_from(by_deadline).unwrap_or(usize::MAX))
+    }
     /// 弹出定时间内的一个定时任务
     /// * @return `Option<T>` 弹出的定时元素
     pub fn pop(&mut self, now: u64) -> Option<T> {
-        loop {
-            if let Some(r) = self.wheel.pop(&mut self.slot) {
-                self.remove_count += 1;
-                return Some(r.el)
-            }
-            if self.roll_count >= now {
-                return None
-            }
-            self.roll();
-        }
+        self.pop_item(now).map(|(_, r)| r.el.el)
     }
     /// 弹出定时间内的一个关键字和定时任务
     /// * @return `Option<(TimerKey, T)>` 弹出的关键字和定时元素
+    #[deprecated(note = "use `pop_at`")]
     pub fn pop_kv(&mut self, now: u64) -> Option<(TimerKey, T)> {
+        self.pop_item(now).map(|(key, r)| (key, r.el.el))
+    }
+    /// 弹出定时间内的一个定时任务，附带请求的超时时间和到期时间，用于诊断调度问题
+    /// * @return `Option<Expired<T>>` 弹出的定时任务
+    pub fn pop_expired(&mut self, now: u64) -> Option<Expired<T>> {
+        self.pop_item(now).map(|(key, r)| Expired {
+            key,
+            timeout: r.el.timeout,
+            deadline: r.el.deadline,
+            #[cfg(feature = "subtick")]
+            remainder: r.el.remainder,
+            el: r.el.el,
+        })
+    }
+    /// 滚动到指定时间，将经过的全部到期任务移入就绪队列，之后用 `pop_ready` 取出
+    /// * @tip 滚动和堆的级联都在这里完成，可以控制每帧的工作量
+    #[deprecated(note = "use `advance_to`")]
+    pub fn advance(&mut self, now: u64) {
+        if self.is_sparse() {
+            while self.sparse_due(now) {}
+            return;
+        }
+        while self.roll_count < now {
+            self.roll();
+        }
+        self.take_cur();
+    }
+    /// 弹出一个已到期的定时任务，不滚动
+    /// * @return `Option<(TimerKey, T)>` 弹出的关键字和定时元素
+    pub fn pop_ready(&mut self) -> Option<(TimerKey, T)> {
+        self.pop_item(self.roll_count).map(|(key, r)| (key, r.el.el))
+    }
+    /// 获得就绪队列中的任务数量
+    pub fn ready_len(&self) -> usize {
+        self.ready_count
+    }
+    /// 先弹出就绪队列，再弹出当前槽位，当前槽位为空时滚动，直到指定时间
+    fn pop_item(&mut self, now: u64) -> Option<(TimerKey, TimeoutItem<Entry<T>>)> {
+        if let Some(key) = self.ready.pop_key_front(&mut self.slot) {
+            self.ready_count -= 1;
+            let r = self.take_popped(key);
+            self.expire(key, r.el.deadline, now);
+            return Some((key, r));
+        }
+        if self.is_sparse() {
+            if !self.sparse_due(now) {
+                return None;
+            }
+            return self.pop_item(now);
+        }
         loop {
-            if let Some((key, r)) = self.wheel.pop_kv(&mut self.slot) {
-                self.remove_count += 1;
-                return Some((key, r.el))
+            let cur = self.wheel.get_slot_mut(self.wheel.roll_count() % N0);
+            if let Some(key) = cur.pop_key_front(&mut self.slot) {
+                let r = self.take_popped(key);
+                self.expire(key, r.el.deadline, now);
+                return Some((key, r));
             }
             if self.roll_count >= now {
-                return None
+                return None;
             }
             self.roll();
         }
     }
+    /// 从slot中取出已摘下的到期任务，开启放回窗口时保留其槽位，使关键字可以用 `requeue` 放回
+    #[inline]
+    fn take_popped(&mut self, key: TimerKey) -> TimeoutItem<Entry<T>> {
+        if self.settings.requeue_window == 0 && self.popped.is_empty() {
+            return self.slot.remove(key).unwrap().el;
+        }
+        let mut node = self.slot.detach(key).unwrap();
+        self.popped.push_back(key);
+        // 超出窗口的关键字借当前节点重新挂上再移除，释放其槽位
+        while self.popped.len() > self.settings.requeue_window {
+            let old = self.popped.pop_front().unwrap();
+            self.slot.reattach(old, node);
+            node = self.slot.remove(old).unwrap();
+        }
+        node.el
+    }
+    /// 记录到期任务的弹出，丢弃其清理函数，并检查延迟
+    #[inline]
+    #[cfg_attr(feature = "minimal", allow(unused_variables))]
+    fn expire(&mut self, key: TimerKey, deadline: u64, now: u64) {
+        debug_assert!(deadline <= self.roll_count, "expired before deadline");
+        self.next.remove(deadline);
+        if !self.hooks.on_cancel.is_empty() {
+            self.hooks.on_cancel.remove(key);
+        }
+        count! {
+            self.counts.expired += 1;
+            let late = now.max(self.roll_count).saturating_sub(deadline);
+            if late > self.settings.late_threshold {
+                self.counts.late += 1;
+                if let Some(f) = &mut self.hooks.on_late {
+                    f(key, late);
+                }
+            }
+        }
+    }
+    /// 将当前槽位的任务移到就绪队列尾部
+    /// * @return `usize` 移动的任务数量
+    fn take_cur(&mut self) -> usize {
+        let deque = self.wheel.get_slot_mut(self.wheel.roll_count() % N0);
+        if deque.head().is_null() {
+            return 0;
+        }
+        let deque = mem::take(deque);
+        let mut count = 0;
+        let mut key = deque.head();
+        while !key.is_null() {
+            let node = node_mut(&mut self.slot, key);
+            node.el.index = READY;
+            key = node.next();
+            count += 1;
+        }
+        self.ready.merge_back(deque, &mut self.slot);
+        self.ready_count += count;
+        count
+    }
+    /// 设置稀疏模式的任务数量阈值，0表示关闭，默认关闭
+    /// * @tip 开启后，任务数量低于阈值且轮和堆为空时，新任务只放入按到期时间排列的稀疏堆，弹出时直接跳到到期时间，不逐次滚动；
+    ///   任务数量达到阈值时，稀疏堆的任务全部移入轮中
+    pub fn set_sparse_threshold(&mut self, threshold: usize) {
+        self.settings.sparse_threshold = threshold;
+        if threshold == 0 {
+            self.densify();
+        }
+    }
+    /// 获得稀疏模式的任务数量阈值
+    pub fn sparse_threshold(&self) -> usize {
+        self.settings.sparse_threshold
+    }
+    /// 是否处于稀疏模式，即开启了稀疏模式且轮和堆都为空
+    fn is_sparse(&self) -> bool {
+        self.is_sparse_except(0)
+    }
+    /// 除去slot中尚未放置的unplaced个任务后，是否处于稀疏模式
+    fn is_sparse_except(&self, unplaced: usize) -> bool {
+        self.settings.sparse_threshold > 0
+            && self.slot.len() == self.ready_count + self.sparse.len() + unplaced
+    }
+    /// 稀疏模式下，将最早的到期任务移入就绪队列，没有到期任务时直接跳到指定时间
+    /// * @return `bool` 是否有到期任务
+    fn sparse_due(&mut self, now: u64) -> bool {
+        match self.sparse.peek() {
+            Some(it) if it.0 .0 <= now => {
+                let Reverse((deadline, _, key)) = self
+                    .sparse
+                    .pop(&mut self.slot, set_sparse_index::<T>)
+                    .unwrap();
+                self.roll_count = self.roll_count.max(deadline);
+                self.push_ready(key);
+                true
+            }
+            _ => {
+                self.roll_count = self.roll_count.max(now);
+                false
+            }
+        }
+    }
+    /// 将slot中已到期的任务追加到就绪队列
+    fn push_ready(&mut self, key: TimerKey) {
+        node_mut(&mut self.slot, key).el.index = READY;
+        self.ready.push_key_back(key, &mut self.slot);
+        self.ready_count += 1;
+    }
+    /// 任务数量达到阈值或关闭稀疏模式时，将稀疏堆的任务按顺序移入轮中
+    fn densify(&mut self) {
+        while let Some(Reverse((deadline, _, key))) =
+            self.sparse.pop(&mut self.slot, set_sparse_index::<T>)
+        {
+            let timeout = deadline.saturating_sub(self.roll_count) as usize;
+            self.place_wheel(key, timeout);
+        }
+    }
+    /// 任务是否应放入稀疏堆，任务数量达到阈值时先将稀疏堆的任务移入轮中
+    /// * @tip unplaced为slot中尚未放置的任务数量，新任务为0，重新放置的任务为1
+    fn use_sparse(&mut self, unplaced: usize) -> bool {
+        if self.settings.sparse_threshold == 0 {
+            return false;
+        }
+        if self.slot.len() >= self.settings.sparse_threshold {
+            self.densify();
+            return false;
+        }
+        self.is_sparse_except(unplaced)
+    }
+    /// 将slot中已有的任务放入稀疏堆
+    fn place_sparse(&mut self, key: TimerKey, timeout: usize) {
+        let node = node_mut(&mut self.slot, key);
+        self.next.retime(&mut node.el.el.deadline, self.roll_count + timeout as u64);
+        let it = Reverse((node.el.el.deadline, node.el.el.seq, key));
+        self.sparse.push(it, &mut self.slot, set_sparse_index::<T>);
+    }
+    /// 弹出定时间内的全部定时任务
+    /// * @return `Vec<(TimerKey, T)>` 按弹出顺序排列的关键字和定时元素
+    pub fn pop_all(&mut self, now: u64) -> Vec<(TimerKey, T)> {
+        let mut vec = Vec::with_capacity(self.due_estimate(now));
+        while let Some(it) = self.pop_item(now).map(|(key, r)| (key, r.el.el)) {
+            vec.push(it);
+        }
+        vec
+    }
+    /// 估计指定时间内到期的任务数量，只统计第0层中当前位置到now之间的槽位，不包括之后从上层级联下来的任务
+    fn due_estimate(&mut self, now: u64) -> usize {
+        let span = now.saturating_sub(self.roll_count).min(N0 as u64 - 1) as usize;
+        let cur = self.wheel.roll_count();
+        self.ready_len()
+            + (0..=span)
+                .map(|i| self.wheel.get_slot_mut((cur + i) % N0).iter(&self.slot).count())
+                .sum::<usize>()
+    }
+    /// 判断指定时间内是否还有定时任务，不滚动
+    /// * @tip 与 `next_deadline` 相同，通常为O(1)
+    pub fn has_due(&self, now: u64) -> bool {
+        self.earliest().is_some_and(|deadline| deadline <= now)
+    }
     /// 判断指定时间内是否还有定时任务
+    /// * @tip 会滚动到第一个有任务的槽位或now为止，只查询不滚动应使用 `has_due`
     pub fn is_ok(&mut self, now: u64) -> bool {
+        if !self.ready.head().is_null() {
+            return true;
+        }
+        if self.is_sparse() {
+            return self.sparse_due(now);
+        }
         loop {
             if !self.wheel.is_cur_over() {
                 return true
@@ -123,67 +1597,637 @@ impl<T, const N0: usize, const N: usize, const L: usize> Timer<T, N0, N, L> {
             self.roll();
         }
     }
+    /// 获得最近的到期时间，不滚动
+    /// * @tip 依次由就绪队列的队首、当前槽位和缓存得出，为O(1)；
+    ///   只有缓存的最近任务被弹出或取消后的第一次查询需要遍历全部定时任务重建缓存
+    #[deprecated(note = "use `next_expiry`")]
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.earliest()
+    }
+    /// 获得最近的到期时间，同 `next_deadline`，供内部使用
+    fn earliest(&self) -> Option<u64> {
+        if self.slot.is_empty() {
+            return None;
+        }
+        // 就绪队列按到期顺序排列，其余任务都不早于当前滚动次数
+        if let Some(node) = self.slot.get(self.ready.head()) {
+            return Some(node.el.el.deadline);
+        }
+        if !self.wheel.is_cur_over() {
+            return Some(self.roll_count);
+        }
+        if let Some(deadline) = self.next.get() {
+            return Some(deadline);
+        }
+        let deadline = self.slot.iter().map(|(_, node)| node.el.el.deadline).min()?;
+        self.next.set(deadline);
+        Some(deadline)
+    }
+    /// 获得距最近的到期时间还需滚动的次数，可作为事件循环的等待时长，已到期时为0
+    #[deprecated(note = "use `next_expiry_in`")]
+    pub fn next_timeout(&self) -> Option<u64> {
+        self.earliest()
+            .map(|deadline| deadline.saturating_sub(self.roll_count))
+    }
+    /// 按到期时间顺序访问指定时间内到期的定时任务，不移除任务也不滚动
+    /// * @tip 需要遍历全部定时任务，适合日志和统计等低频场景
+    pub fn for_each_due<F>(&self, now: u64, mut f: F)
+    where
+        F: FnMut(TimerKey, u64, &T),
+    {
+        let mut vec: Vec<_> = self
+            .slot
+            .iter()
+            .filter(|(_, node)| node.el.el.deadline <= now)
+            .map(|(key, node)| (node.el.el.deadline, node.el.el.seq, key, &node.el.el.el))
+            .collect();
+        vec.sort_unstable_by_key(|e| (e.0, e.1));
+        for (deadline, _, key, el) in vec {
+            f(key, deadline, el);
+        }
+    }
     /// 轮滚动 - 向后滚动一个最小粒度, 可能会造成轮的逐层滚动。如果滚动到底，则修正堆上全部的定时任务，并将堆上的到期任务放入轮中
+    /// * @tip 当前槽位中尚未弹出的任务会先移入就绪队列，不会被跳过
     pub fn roll(&mut self) {
+        let due = self.take_cur();
+        self.step(due);
+    }
+    /// 滚动n次，经过的和最终位置上的到期任务都移入就绪队列，之后用 `pop_ready` 取出
+    /// * @return `RollReport` 到期、级联的任务数量和是否滚动到底
+    pub fn roll_n(&mut self, n: u64) -> RollReport {
+        let mut report = RollReport::default();
+        for _ in 0..n {
+            let due = self.take_cur();
+            let r = self.step(due);
+            report.due += due;
+            report.cascaded += r.cascaded;
+            report.wrapped |= r.wrapped;
+        }
+        report.due += self.take_cur();
+        report
+    }
+    /// 滚动一次，不处理当前槽位中的任务，due为滚动前移入就绪队列的任务数量
+    /// * @return `RollReport` 本次滚动的报告
+    fn step(&mut self, due: usize) -> RollReport {
+        let mut cascaded = 0;
         self.roll_count += 1;
-        if self.wheel.roll(&mut self.slot) {
-            // 修正堆上全部的定时任务
-            for i in 0..self.heap.len() {
-                unsafe { self.heap.get_unchecked_mut(i).0.0 -= self.wheel.max_time() };
+        let wrapped = self.wheel.roll(&mut self.slot);
+        if wrapped {
+            // 修正堆上全部的定时任务，延迟修正时只累加基准
+            if self.settings.lazy_restamp {
+                self.heap_base += self.wheel.max_time();
+            } else {
+                self.restamp(self.wheel.max_time());
             }
             // 如果滚到轮的最后一层的最后一个， 则将堆上的到期任务放入轮中
-            // 检查堆顶的最近的任务
+            // 先检查内联的任务，再检查堆顶的最近的任务
+            let mut i = 0;
+            while i < self.inline.len {
+                let (timeout, key) = self.inline.items[i];
+                if timeout - self.heap_base >= self.wheel.max_time() {
+                    i += 1;
+                    continue;
+                }
+                self.remove_inline(i);
+                cascaded += 1;
+                self.wheel
+                    .push_key(key, &mut self.slot, &mut (timeout - self.heap_base), retimeout);
+            }
             while let Some(it) = self.heap.peek() {
                 // 判断任务是否需要放入轮中
-                if it.0.0 >= self.wheel.max_time() {
+                if it.0 - self.heap_base >= self.wheel.max_time() {
                     break;
                 }
-                let Reverse((mut timeout, key)) = self
-                    .heap
-                    .pop(&mut self.slot, set_index::<T, N0, N, L>)
-                    .unwrap();
+                let (mut timeout, key) = self.heap.pop::<T, N0, N, L>(&mut self.slot).unwrap();
+                timeout -= self.heap_base;
                 // 时间已经修正过了，可以直接放入定时轮中
+                cascaded += 1;
                 self.wheel
                     .push_key(key, &mut self.slot, &mut timeout, retimeout);
             }
         }
+        self.sort_cur();
+        count!(self.counts.cascade += cascaded);
+        let report = RollReport {
+            due,
+            cascaded,
+            wrapped,
+        };
+        #[cfg(not(feature = "minimal"))]
+        if let Some(f) = &mut self.hooks.on_roll {
+            f(self.roll_count, report);
+        }
+        report
+    }
+    /// 将堆上全部定时任务的时间减去指定值，统一减去相同的值不会改变堆的顺序
+    fn restamp(&mut self, delta: usize) {
+        for it in self.inline.items[..self.inline.len].iter_mut() {
+            it.0 = overflow::sub(it.0, delta);
+        }
+        self.heap.restamp::<T, N0, N, L>(delta, &mut self.slot);
+    }
+    /// 将当前槽位的任务按放入序号排序，轮的级联和堆的级联都是追加在槽位尾部，可能打乱先后顺序
+    fn sort_cur(&mut self) {
+        let deque = self.wheel.get_slot_mut(self.wheel.roll_count() % N0);
+        let mut seq = 0;
+        let mut key = deque.head();
+        while !key.is_null() {
+            let node = node(&self.slot, key);
+            if node.el.el.seq < seq {
+                break;
+            }
+            seq = node.el.el.seq;
+            key = node.next();
+        }
+        if key.is_null() {
+            return;
+        }
+        // 复用排序缓冲，稳定运行时不再分配内存
+        let keys = &mut self.sort_buf;
+        let mut key = deque.head();
+        while !key.is_null() {
+            let node = node(&self.slot, key);
+            keys.push((node.el.el.seq, key));
+            key = node.next();
+        }
+        keys.sort_unstable();
+        *deque = Default::default();
+        for (_, key) in keys.drain(..) {
+            deque.push_key_back(key, &mut self.slot);
+        }
+    }
+    /// 取出全部定时任务，按到期时间排序，到期时间相同的按放入顺序
+    /// * @return 迭代器，元素为 `(到期时间, 关键字, 定时元素)`
+    pub fn drain_sorted(&mut self) -> DrainSorted<T> {
+        let vec: Vec<_> = self
+            .drain_entries()
+            .into_iter()
+            .map(|(key, e)| (e.deadline, e.seq, key, e.el))
+            .collect();
+        count!(self.counts.cancelled += vec.len() as u64);
+        vec.into_iter().map(|(deadline, _, key, el)| (deadline, key, el))
+    }
+    /// 取出全部定时条目，按到期时间和放入顺序排列，之后重置轮、堆和就绪队列
+    fn drain_entries(&mut self) -> Vec<(TimerKey, Entry<T>)> {
+        let mut vec: Vec<_> = self
+            .slot
+            .drain()
+            .map(|(key, node)| (key, node.el.el))
+            .collect();
+        self.reset();
+        vec.sort_unstable_by_key(|(_, e)| (e.deadline, e.seq));
+        vec
+    }
+    /// slot清空后重置轮、堆和就绪队列
+    fn reset(&mut self) {
+        self.wheel = Default::default();
+        self.heap.clear();
+        self.inline.len = 0;
+        self.sparse.clear();
+        self.ready = Default::default();
+        self.ready_count = 0;
+        self.heap_base = 0;
+        self.hooks.on_cancel.clear();
+        self.next.clear();
+    }
+    /// 关闭定时器，之后不再接受新的定时任务，弹出指定时间内全部到期的任务，并按到期时间顺序取出其余任务
+    /// * @tip 关闭后 `try_push` 返回 `PushError::Shutdown`，`push` 等不可失败的放入交给销毁时的钩子函数或直接丢弃
+    pub fn shutdown(&mut self, now: u64) -> ShutdownReport<T> {
+        self.shutdown = true;
+        let mut expired = Vec::new();
+        while let Some(it) = self.pop_item(now).map(|(key, r)| (key, r.el.el)) {
+            expired.push(it);
+        }
+        ShutdownReport {
+            expired,
+            pending: self.drain_sorted().collect(),
+        }
+    }
+    /// 定时器是否已关闭
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+    /// 将另一个定时器的全部定时任务合并进来，两个定时器的滚动次数应基于同一个时间起点
+    /// * @tip 定时器关闭后同 `merge_from`
+    /// * @return 关键字映射表，元素为 `(原关键字, 新关键字)`
+    pub fn merge(&mut self, other: Self) -> Vec<(TimerKey, TimerKey)> {
+        self.merge_from(other)
+    }
+    /// 将另一个不同轮结构的定时器的全部定时任务合并进来，到期时间保持不变
+    /// * @tip 按原到期时间放入，不经过当前定时器的合并窗口、取整和截断；已过期的任务按到期时间插入就绪队列。
+    ///   定时器关闭后不再放入，调用任务的清理函数后交给销毁时的钩子函数或直接丢弃，新关键字为空关键字
+    /// * @return 关键字映射表，元素为 `(原关键字, 新关键字)`
+    pub fn merge_from<const M0: usize, const M: usize, const ML: usize>(
+        &mut self,
+        mut other: Timer<T, M0, M, ML>,
+    ) -> Vec<(TimerKey, TimerKey)> {
+        // 清理函数随任务一起转移
+        let mut on_cancel = mem::take(&mut other.hooks.on_cancel);
+        other
+            .drain_entries()
+            .into_iter()
+            .map(|(key, entry)| {
+                if self.shutdown {
+                    let mut el = entry.el;
+                    if let Some(f) = on_cancel.remove(key) {
+                        f(key, &mut el);
+                    }
+                    return (key, self.refuse(key, el));
+                }
+                let new = self.restore(entry);
+                if let Some(f) = on_cancel.remove(key) {
+                    self.hooks.on_cancel.insert(new, f);
+                }
+                (key, new)
+            })
+            .collect()
+    }
+    /// 将满足条件的定时任务分离到一个新的定时器中，新定时器的滚动次数和设置与当前定时器相同
+    /// * @tip 分离是转移而不是取消，不计入取消数量，也不调用清理函数，清理函数和到期时间随任务转移
+    /// * @return 新的定时器和关键字映射表，映射表元素为 `(原关键字, 新关键字)`
+    pub fn split_off<F>(&mut self, mut pred: F) -> (Self, Vec<(TimerKey, TimerKey)>)
+    where
+        F: FnMut(TimerKey, &T) -> bool,
+    {
+        let mut keys: Vec<_> = self
+            .slot
+            .iter()
+            .filter(|(key, node)| pred(*key, &node.el.el.el))
+            .map(|(key, node)| (node.el.el.deadline, node.el.el.seq, key))
+            .collect();
+        keys.sort_unstable();
+        let mut timer = Self::default();
+        timer.roll_count = self.roll_count;
+        timer.settings = self.settings;
+        let map = keys
+            .into_iter()
+            .map(|(_, _, key)| {
+                let f = self.hooks.on_cancel.remove(key);
+                self.unlink(key);
+                let entry = self.slot.remove(key).unwrap().el.el;
+                self.next.remove(entry.deadline);
+                let new = timer.restore(entry);
+                if let Some(f) = f {
+                    timer.hooks.on_cancel.insert(new, f);
+                }
+                (key, new)
+            })
+            .collect();
+        (timer, map)
+    }
+    /// 紧凑重建slot，消除长期增删造成的空洞，全部定时任务的到期时间和先后顺序保持不变
+    /// * @tip 每个任务都会换成新的关键字，通过remap通知 `(原关键字, 新关键字)`，清理函数随任务转移
+    pub fn compact<F: FnMut(TimerKey, TimerKey)>(&mut self, mut remap: F) {
+        let mut vec: Vec<_> = self
+            .slot
+            .drain()
+            .map(|(key, node)| (key, node.el.el))
+            .collect();
+        vec.sort_unstable_by_key(|(_, e)| (e.deadline, e.seq));
+        let mut on_cancel = mem::take(&mut self.hooks.on_cancel);
+        self.slot = Slot::with_capacity_and_key(vec.len());
+        // 保留的弹出槽位随旧的slot一起丢弃
+        self.popped.clear();
+        self.reset();
+        for (key, entry) in vec {
+            let deadline = entry.deadline;
+            let timeout = deadline.saturating_sub(self.roll_count) as usize;
+            let new = self.slot.insert(LinkedNode::new(
+                TimeoutItem::new(timeout, entry, 0),
+                TimerKey::null(),
+                TimerKey::null(),
+            ));
+            if deadline < self.roll_count {
+                // 已过期的任务按到期顺序放入就绪队列，保留原来的到期时间
+                self.push_ready(new);
+            } else {
+                self.place_wheel(new, timeout);
+            }
+            if let Some(f) = on_cancel.remove(key) {
+                self.hooks.on_cancel.insert(new, f);
+            }
+            remap(key, new);
+        }
+    }
+    /// 以新的轮结构重建定时器，全部定时任务的关键字和到期时间保持不变
+    pub fn reconfigure<const M0: usize, const M: usize, const ML: usize>(
+        mut self,
+    ) -> Timer<T, M0, M, ML> {
+        let mut keys: Vec<_> = self
+            .slot
+            .iter()
+            .map(|(key, node)| (node.el.el.deadline, node.el.el.seq, key))
+            .collect();
+        keys.sort_unstable();
+        let () = Timer::<T, M0, M, ML>::LAYOUT;
+        let mut timer = Timer {
+            slot: mem::take(&mut self.slot),
+            wheel: Default::default(),
+            heap: Default::default(),
+            inline: Default::default(),
+            ready: Default::default(),
+            ready_count: 0,
+            sparse: Default::default(),
+            heap_base: 0,
+            counts: self.counts,
+            roll_count: self.roll_count,
+            seq: self.seq,
+            hooks: mem::take(&mut self.hooks),
+            shutdown: self.shutdown,
+            settings: self.settings,
+            sort_buf: mem::take(&mut self.sort_buf),
+            next: Default::default(),
+            popped: mem::take(&mut self.popped),
+        };
+        for (deadline, _, key) in keys {
+            let mut timeout = deadline.saturating_sub(timer.roll_count) as usize;
+            if deadline < timer.roll_count {
+                timer.push_ready(key);
+            } else if timeout < timer.wheel.max_time() {
+                // 新的轮没有滚动过，可以直接按键放入
+                timer
+                    .wheel
+                    .push_key(key, &mut timer.slot, &mut timeout, retimeout);
+            } else {
+                timer.push_overflow(timeout, key);
+            }
+        }
+        timer
+    }
+    /// 查看定时间内的一个到期任务，不弹出
+    /// * @tip 可以用 `cancel` 取走该任务，或用 `reschedule` 保持关键字不变重新定时
+    /// * @return `Option<(TimerKey, &T)>` 到期的关键字和定时元素
+    pub fn peek_kv(&mut self, now: u64) -> Option<(TimerKey, &T)> {
+        if !self.is_ok(now) {
+            return None;
+        }
+        let mut key = self.ready.head();
+        if key.is_null() {
+            key = self.wheel.get_slot_mut(self.wheel.roll_count() % N0).head();
+        }
+        self.slot.get(key).map(|node| (key, &node.el.el.el))
+    }
+    /// 设置保留可放回的最近弹出的关键字数量，默认为0即弹出后立即回收
+    /// * @tip 窗口内的关键字的槽位不会被复用，可以用 `requeue` 按原关键字放回；超出窗口时回收最早弹出的关键字
+    pub fn set_requeue_window(&mut self, window: usize) {
+        self.settings.requeue_window = window;
+    }
+    /// 获得保留可放回的最近弹出的关键字数量
+    pub fn requeue_window(&self) -> usize {
+        self.settings.requeue_window
+    }
+    /// 将已弹出的定时任务按原关键字和新的超时时间放回，用于弹出后暂时无法处理的任务
+    /// * @tip 只能放回 `set_requeue_window` 窗口内最近弹出的关键字，且每个关键字只能放回一次；
+    ///   检查与 `try_push` 相同
+    /// * @return 关键字不在窗口内时返回 `PushError::Stale`，其余错误同 `try_push`
+    pub fn requeue(
+        &mut self,
+        key: TimerKey,
+        el: T,
+        timeout: usize,
+    ) -> std::result::Result<(), PushError<T>> {
+        let Some(i) = self.popped.iter().rposition(|k| *k == key) else {
+            return Err(PushError::Stale(el));
+        };
+        if let Err(e) = self.check_push(timeout) {
+            return Err(e(el));
+        }
+        self.popped.remove(i);
+        let adjusted = self.adjust(timeout, self.settings.rounding);
+        let entry = self.entry(timeout, adjusted, el);
+        self.next.push(entry.deadline, self.slot.is_empty());
+        let node = LinkedNode::new(
+            TimeoutItem::new(0, entry, 0),
+            TimerKey::null(),
+            TimerKey::null(),
+        );
+        self.slot.reattach(key, node);
+        self.place(key, adjusted);
+        count!(self.counts.max_len = self.counts.max_len.max(self.slot.len()));
+        Ok(())
+    }
+    /// 将未弹出的定时任务按新的超时时间重新定时，关键字保持不变
+    /// * @tip 已弹出的任务用 `requeue` 放回
+    /// * @return `bool` 任务是否存在
+    pub fn reschedule(&mut self, key: TimerKey, timeout: usize) -> bool {
+        if !self.unlink(key) {
+            return false;
+        }
+        self.seq += 1;
+        let node = node_mut(&mut self.slot, key);
+        node.el.el.seq = self.seq;
+        node.el.el.timeout = timeout;
+        self.place(key, self.adjust(timeout, self.settings.rounding));
+        true
     }
     /// 取消定时任务
     pub fn cancel(&mut self, key: TimerKey) -> Option<T> {
-        match self.slot.remove(key) {
-            Some(node) => {
-                self.remove_count += 1;
-                if node.el.index < N0 + N * L {
-                    self.wheel.get_slot_mut(node.el.index).repair(
-                        node.prev(),
-                        node.next(),
-                        &mut self.slot,
-                    );
-                } else {
-                    self.heap.remove(
-                        node.el.index - N0 - N * L,
-                        &mut self.slot,
-                        set_index::<T, N0, N, L>,
-                    );
+        #[cfg_attr(feature = "minimal", allow(unused_variables))]
+        let index = self.slot.get(key)?.el.index;
+        if !self.unlink(key) {
+            return None;
+        }
+        count! {
+            self.counts.cancelled += 1;
+            if index == READY || index < N0 + N * L {
+                self.counts.cancel_wheel += 1;
+            } else {
+                self.counts.cancel_heap += 1;
+            }
+        }
+        let entry = self.slot.remove(key)?.el.el;
+        self.next.remove(entry.deadline);
+        let mut el = entry.el;
+        if let Some(f) = self.hooks.on_cancel.remove(key) {
+            f(key, &mut el);
+        }
+        Some(el)
+    }
+    /// 按条目原有的到期时间放入，不调整超时时间，条目的请求超时时间和附加数据保持不变
+    fn restore(&mut self, mut entry: Entry<T>) -> TimerKey {
+        debug_assert!(!self.shutdown);
+        count!(self.counts.add += 1);
+        self.seq += 1;
+        entry.seq = self.seq;
+        let deadline = entry.deadline;
+        self.next.push(deadline, self.slot.is_empty());
+        let key = self.slot.insert(LinkedNode::new(
+            TimeoutItem::new(0, entry, 0),
+            TimerKey::null(),
+            TimerKey::null(),
+        ));
+        if deadline < self.roll_count {
+            self.push_ready_sorted(key);
+        } else {
+            self.place(key, (deadline - self.roll_count) as usize);
+        }
+        count!(self.counts.max_len = self.counts.max_len.max(self.slot.len()));
+        key
+    }
+    /// 将已过期的任务按到期时间插入就绪队列，到期时间相同的排在已有任务之后
+    fn push_ready_sorted(&mut self, key: TimerKey) {
+        let deadline = node_mut(&mut self.slot, key).el.el.deadline;
+        let mut later = Vec::new();
+        while let Some(node) = self.slot.get(self.ready.tail()) {
+            if node.el.el.deadline <= deadline {
+                break;
+            }
+            later.push(self.ready.pop_key_back(&mut self.slot).unwrap());
+        }
+        node_mut(&mut self.slot, key).el.index = READY;
+        self.ready.push_key_back(key, &mut self.slot);
+        for k in later.into_iter().rev() {
+            self.ready.push_key_back(k, &mut self.slot);
+        }
+        self.ready_count += 1;
+    }
+    /// 将任务从所在的轮槽位或堆上摘下，任务仍保留在slot中
+    fn unlink(&mut self, key: TimerKey) -> bool {
+        let (index, prev, next) = match self.slot.get(key) {
+            Some(node) => (node.el.index, node.prev(), node.next()),
+            _ => return false,
+        };
+        if index == READY {
+            self.ready.repair(prev, next, &mut self.slot);
+            self.ready_count -= 1;
+        } else if index >= SPARSE {
+            let index = index - SPARSE;
+            self.sparse.remove(index, &mut self.slot, set_sparse_index::<T>);
+            // 堆的删除只会向下调整，换到删除位置的末尾元素可能需要向上调整
+            if index < self.sparse.len() {
+                self.sparse.repair(
+                    index,
+                    Ordering::Greater,
+                    &mut self.slot,
+                    set_sparse_index::<T>,
+                );
+            }
+        } else if index < N0 + N * L {
+            self.wheel
+                .get_slot_mut(index)
+                .repair(prev, next, &mut self.slot);
+        } else if index < N0 + N * L + INLINE {
+            self.remove_inline(index - N0 - N * L);
+        } else {
+            let loc = index - overflow::base::<N0, N, L>();
+            self.heap.remove::<T, N0, N, L>(loc, key, &mut self.slot);
+        }
+        true
+    }
+    /// 将slot中已有的任务按超时时间放入轮、堆或稀疏堆中
+    fn place(&mut self, key: TimerKey, timeout: usize) {
+        if self.use_sparse(1) {
+            self.place_sparse(key, timeout);
+        } else {
+            self.place_wheel(key, timeout);
+        }
+    }
+    /// 将slot中已有的任务按超时时间放入轮或堆中，计算方式与定时轮的放入相同
+    fn place_wheel(&mut self, key: TimerKey, timeout: usize) {
+        // 定时轮在一圈内的滚动位置
+        let pos = self.wheel.roll_count();
+        let node = node_mut(&mut self.slot, key);
+        self.next.retime(&mut node.el.el.deadline, self.roll_count + timeout as u64);
+        if timeout < N0 {
+            let j = (pos + timeout) % N0;
+            node.el.timeout = timeout;
+            node.el.index = j;
+            self.wheel.get_slot_mut(j).push_key_back(key, &mut self.slot);
+            return;
+        }
+        for i in 0..L {
+            let t = N0 * N.pow(i as u32);
+            if timeout < t * N {
+                let timeout = (timeout + pos % (t * N)) % (t * N);
+                let index = N0 + i * N + timeout / t;
+                node.el.timeout = timeout;
+                node.el.index = index;
+                self.wheel
+                    .get_slot_mut(index)
+                    .push_key_back(key, &mut self.slot);
+                return;
+            }
+        }
+        self.push_overflow(timeout + pos + self.heap_base, key);
+    }
+    /// 将超出定时轮的任务按绝对时间放入内联数组，满了则放入堆中
+    fn push_overflow(&mut self, timeout: usize, key: TimerKey) {
+        if self.inline.len < INLINE {
+            let loc = self.inline.len;
+            self.inline.items[loc] = (timeout, key);
+            self.inline.len += 1;
+            node_mut(&mut self.slot, key).el.index = N0 + N * L + loc;
+        } else {
+            self.heap.push::<T, N0, N, L>(timeout, key, &mut self.slot);
+        }
+        self.heap_pushed();
+    }
+    /// 移除内联数组中指定位置的任务，末尾的任务换到该位置
+    fn remove_inline(&mut self, loc: usize) {
+        self.inline.len -= 1;
+        let last = self.inline.len;
+        if loc != last {
+            self.inline.items[loc] = self.inline.items[last];
+            node_mut(&mut self.slot, self.inline.items[loc].1).el.index = N0 + N * L + loc;
+        }
+    }
+    /// 放入堆后更新堆的最大长度，堆长度达到水位线时调用堆压力的钩子函数
+    #[inline]
+    fn heap_pushed(&mut self) {
+        count! {
+            let len = self.heap_len();
+            self.counts.max_heap_len = self.counts.max_heap_len.max(len);
+            if len == self.settings.heap_watermark {
+                if let Some(f) = &mut self.hooks.on_overflow_pressure {
+                    f(len);
                 }
-                Some(node.el.el)
             }
-            _ => None,
         }
     }
 }
-fn retimeout<T>(timeout: &mut usize, it: &mut TimeoutItem<T>) {
+/// 计算轮结构能容纳的最大定时时间，首层N0个槽，后面L层每层N个槽
+pub const fn wheel_capacity(n0: usize, n: usize, l: usize) -> usize {
+    n0 * n.pow(l as u32)
+}
+/// 获得slot中的节点，strict特性下检查关键字，无效时panic
+#[inline]
+fn node<T>(
+    slot: &Slot<TimerKey, TimeoutItem<Entry<T>>>,
+    key: TimerKey,
+) -> &LinkedNode<TimerKey, TimeoutItem<Entry<T>>> {
+    #[cfg(feature = "strict")]
+    return slot
+        .get(key)
+        .unwrap_or_else(|| panic!("invalid timer key: {:?}", key));
+    #[cfg(not(feature = "strict"))]
+    unsafe {
+        slot.get_unchecked(key)
+    }
+}
+/// 获得slot中的可变节点，strict特性下检查关键字，无效时panic
+#[inline]
+fn node_mut<T>(
+    slot: &mut Slot<TimerKey, TimeoutItem<Entry<T>>>,
+    key: TimerKey,
+) -> &mut LinkedNode<TimerKey, TimeoutItem<Entry<T>>> {
+    #[cfg(feature = "strict")]
+    return slot
+        .get_mut(key)
+        .unwrap_or_else(|| panic!("invalid timer key: {:?}", key));
+    #[cfg(not(feature = "strict"))]
+    unsafe {
+        slot.get_unchecked_mut(key)
+    }
+}
+fn retimeout<T>(timeout: &mut usize, it: &mut TimeoutItem<Entry<T>>) {
     it.timeout = *timeout;
 }
-fn set_index<T, const N0: usize, const N: usize, const L: usize>(
-    slot: &mut Slot<TimerKey, TimeoutItem<T>>,
-    arr: &mut [Reverse<(usize, TimerKey)>],
+fn set_sparse_index<T>(
+    slot: &mut Slot<TimerKey, TimeoutItem<Entry<T>>>,
+    arr: &mut [Reverse<(u64, u64, TimerKey)>],
     loc: usize,
 ) {
-    let i = &arr[loc];
-    unsafe {
-        slot.get_unchecked_mut(i.0 .1).el.index = N0 + N * L + loc;
-    }
+    node_mut(slot, arr[loc].0 .2).el.index = SPARSE + loc;
 }
 
 
@@ -199,6 +2243,7 @@ mod test_mod {
     };
 
     use self::rand_core::{RngCore, SeedableRng};
+    use crate::tick::Tick;
     use crate::*;
 
     #[test]
@@ -206,7 +2251,7 @@ mod test_mod {
         let mut timer: Timer<(u64, u64), 128, 16, 1> = Default::default();
         let mut rng = pcg_rand::Pcg32::seed_from_u64(22222);
         let start = Instant::now();
-        println!("max_time:{}", timer.wheel.max_time());
+        println!("max_time:{}", timer.max_time());
         for i in 1..100000 {
             let t = (rng.next_u32() % 16100) as u64;
             let now = Instant::now();
@@ -223,16 +2268,900 @@ mod test_mod {
                 let it = timer.pop(tt).unwrap();
                 println!("ppp:{:?}, now:{}", it, tt);
             }
-            if i > 100 && timer.add_count == timer.remove_count {
+            #[cfg(feature = "minimal")]
+            if i > 100 && timer.is_empty() {
+                return;
+            }
+            #[cfg(not(feature = "minimal"))]
+            if i > 100 && timer.add_count() == timer.remove_count() {
                 //println!("vec:{:?}", vec);
                 println!(
                     "return: add_count:{:?}",
-                    timer.add_count
+                    timer.add_count()
                 );
                 return;
             }
-            thread::sleep(Duration::from_millis(1 as u64));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_fifo() {
+        // 轮很小，让大部分任务经过上层轮或堆的级联
+        let mut timer: Timer<(u64, usize), 4, 4, 2> = Default::default();
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(33333);
+        let mut popped = vec![];
+        for i in 0..2000 {
+            let timeout = (rng.next_u32() % 100) as usize;
+            let deadline = timer.now().get() + timeout as u64;
+            timer.push(timeout, (deadline, i));
+            let now = timer.now().get() + (rng.next_u32() % 3) as u64;
+            while let Some(it) = timer.pop(now) {
+                popped.push(it);
+            }
+        }
+        let end = timer.now().get() + 100;
+        while let Some(it) = timer.pop(end) {
+            popped.push(it);
+        }
+        assert_eq!(popped.len(), 2000);
+        for w in popped.windows(2) {
+            assert!(w[0] < w[1], "{:?}", w);
+        }
+    }
+
+    #[test]
+    fn test_deadline_order() {
+        // 混合合并窗口、重新定时、直接滚动和延迟修正，弹出的到期时间都不应递减
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.set_coalesce(3);
+        timer.set_lazy_restamp(true);
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(152);
+        let mut keys = Vec::new();
+        let mut last = 0;
+        for i in 0..3000 {
+            keys.push(timer.push((rng.next_u32() % 120) as usize, i));
+            match rng.next_u32() % 4 {
+                0 => {
+                    let k = keys[rng.next_u32() as usize % keys.len()];
+                    timer.reschedule(k, (rng.next_u32() % 50) as usize);
+                }
+                1 => timer.roll(),
+                _ => (),
+            }
+            let now = timer.now().get() + (rng.next_u32() % 3) as u64;
+            while let Some(e) = timer.pop_expired(now) {
+                assert!(e.deadline >= last, "{} < {}", e.deadline, last);
+                last = e.deadline;
+            }
+        }
+        // 同一次弹出中混合就绪队列、从溢出存储级联下来的和直接放入轮中的任务，
+        // 按到期时间弹出，到期时间相同时按放入顺序
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let index = |t: &Timer<u32, 4, 4, 1>, k: TimerKey| t.slot.get(k).unwrap().el.index;
+        // 超出内联数组的两个任务放入溢出存储
+        let overflow: Vec<_> = (0..6).map(|i| timer.push(40, i)).collect();
+        assert!(overflow.iter().all(|k| (8..READY).contains(&index(&timer, *k))));
+        assert!(index(&timer, overflow[5]) >= overflow::base::<4, 4, 1>());
+        timer.push(3, 6);
+        timer.push(3, 7);
+        for _ in 0..5 {
+            timer.roll();
+        }
+        timer.push(35, 8);
+        while timer.now().get() < 38 {
+            timer.roll();
+        }
+        let wheel = timer.push(2, 9);
+        let ready = timer.push(0, 10);
+        assert!(overflow.iter().all(|k| index(&timer, *k) < 8));
+        assert!(index(&timer, wheel) < 8);
+        assert_eq!(index(&timer, ready), READY);
+        let order: Vec<_> = std::iter::from_fn(|| timer.pop_expired(40))
+            .map(|e| (e.deadline, e.el))
+            .collect();
+        let mut expect = vec![(3, 6), (3, 7), (38, 10)];
+        expect.extend((0..6).chain(8..10).map(|i| (40, i)));
+        assert_eq!(order, expect);
+    }
+
+    #[test]
+    fn test_wrap() {
+        // 在轮的各个位置放入轮边界附近的任务，每个任务都应在到期时刻准时弹出
+        for start in 0..20 {
+            let mut timer: Timer<u64, 4, 2, 1> = Default::default();
+            for _ in 0..start {
+                timer.roll();
+            }
+            for timeout in 0..40 {
+                timer.push(timeout, start + timeout as u64);
+            }
+            for now in start..start + 50 {
+                while let Some(deadline) = timer.pop(now) {
+                    assert_eq!(deadline, now);
+                }
+            }
+            #[cfg(not(feature = "minimal"))]
+            assert_eq!(timer.expired_count(), 40);
+            assert!(timer.is_empty());
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "minimal"))]
+    fn test_stats() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.push(2, 1);
+        let key = timer.push(100, 2);
+        timer.push(3, 3);
+        timer.cancel(key);
+        timer.roll_n(5);
+        let stats = timer.stats();
+        assert_eq!(stats.add, 3);
+        assert_eq!(stats.cancelled, 1);
+        assert_eq!(stats.heap_cancel, 1);
+        assert_eq!((stats.len, stats.ready_len, stats.max_len), (2, 2, 3));
+        assert_eq!(stats.roll_count, 5);
+        assert_eq!(stats.lag, 3);
+        timer.pop_ready();
+        timer.pop_ready();
+        let stats = timer.stats();
+        assert_eq!((stats.expired, stats.len, stats.lag), (2, 0, 0));
+    }
+
+    #[test]
+    fn test_max_time() {
+        const _: () = assert!(Timer::<u32, 64, 64, 3>::fits_wheel(86_400_000 / 10));
+        const _: () = assert!(wheel_capacity(100, 10, 2) == 10_000);
+        assert_eq!(Timer::<u32, 4, 4, 1>::MAX_TIME, 16);
+        let timer: Timer<u32, 6, 3, 2> = Default::default();
+        assert_eq!(timer.max_time(), Wheel::<u32, 6, 3, 2>::default().max_time());
+        assert!(!Timer::<u32, 4, 4, 1>::fits_wheel(16));
+    }
+
+    #[test]
+    fn test_with_start() {
+        let mut timer: Timer<u32, 4, 4, 1> = Timer::with_start(1_000_000);
+        timer.push(3, 0);
+        timer.push_at(Tick(1_000_020), 1);
+        assert!(!timer.set_roll_count(0));
+        assert_eq!(timer.next_expiry().map(Tick::get), Some(1_000_003));
+        assert_eq!(timer.pop(1_000_002), None);
+        assert_eq!(timer.pop(1_000_003), Some(0));
+        assert_eq!(timer.pop(1_000_020), Some(1));
+        assert!(timer.set_roll_count(5));
+        timer.push(1, 2);
+        assert_eq!(timer.pop(6), Some(2));
+    }
+
+    #[test]
+    fn test_pop_all() {
+        let mut timer: Timer<u32, 8, 8, 1> = Default::default();
+        for i in 0..200 {
+            timer.push((i % 100) as usize, i);
+        }
+        let vec = timer.pop_all(5);
+        assert_eq!(
+            vec.iter().map(|r| r.1).collect::<Vec<_>>(),
+            [0, 100, 1, 101, 2, 102, 3, 103, 4, 104, 5, 105]
+        );
+        // 6和7在第0层，8之后还在第1层
+        assert_eq!(timer.due_estimate(13), 4);
+        assert_eq!(timer.pop_all(200).len(), 188);
+        assert!(timer.pop_all(300).is_empty());
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.push(1, 1);
+        timer.push(50, 2);
+        timer.push(3, 3);
+        let report = timer.shutdown(2);
+        assert_eq!(report.expired.iter().map(|e| e.1).collect::<Vec<_>>(), [1]);
+        let pending: Vec<_> = report.pending.iter().map(|e| (e.0, e.2)).collect();
+        assert_eq!(pending, [(3, 3), (50, 2)]);
+        assert!(timer.is_shutdown());
+        assert!(timer.is_empty());
+        assert!(matches!(timer.try_push(1, 4), Err(PushError::Shutdown(4))));
+        assert!(matches!(timer.try_push_time(10, 5), Err(PushError::Shutdown(5))));
+    }
+
+    #[test]
+    fn test_push_after_shutdown() {
+        use std::sync::{Arc, Mutex};
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let d = dropped.clone();
+        timer.set_on_drop(move |_, el| d.lock().unwrap().push(el));
+        timer.shutdown(0);
+        // 关闭后不可失败的放入不panic，定时元素交给销毁时的钩子函数
+        assert!(timer.push(1, 1).is_null());
+        assert!(timer.push_at(Tick(5), 2).is_null());
+        let mut other: Timer<u32, 4, 4, 1> = Default::default();
+        let k = other.push(3, 3);
+        other.set_on_cancel(k, |_, el| *el += 10);
+        let map = timer.merge(other);
+        assert_eq!(map.len(), 1);
+        assert!(map[0].1.is_null());
+        assert!(timer.is_empty());
+        assert_eq!(*dropped.lock().unwrap(), [1, 2, 13]);
+        assert!(timer.cancel(TimerKey::null()).is_none());
+    }
+
+    #[test]
+    fn test_has_due() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        assert!(!timer.has_due(100));
+        timer.push(30, 1);
+        timer.push(10, 0);
+        assert!(!timer.has_due(9));
+        assert!(timer.has_due(10));
+        assert_eq!(timer.now().get(), 0);
+        assert!(timer.is_ok(10));
+        assert_eq!(timer.now().get(), 10);
+        timer.pop(10);
+        assert!(!timer.has_due(29));
+        assert!(timer.has_due(30));
+        // 最近到期时间的缓存与遍历的结果一致
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(148);
+        let mut keys = Vec::new();
+        for sparse in [0, 16] {
+            let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+            timer.set_sparse_threshold(sparse);
+            for i in 0..20000 {
+                match rng.next_u32() % 8 {
+                    0 | 1 => keys.push(timer.push((rng.next_u32() % 60) as usize, i)),
+                    2 if !keys.is_empty() => {
+                        let k = keys.swap_remove(rng.next_u32() as usize % keys.len());
+                        timer.cancel(k);
+                    }
+                    3 if !keys.is_empty() => {
+                        let k = keys[rng.next_u32() as usize % keys.len()];
+                        timer.reschedule(k, (rng.next_u32() % 60) as usize);
+                    }
+                    4 => timer.roll(),
+                    5 => {
+                        timer.pop_ready();
+                    }
+                    6 if i % 1000 == 0 => timer.compact(|_, _| keys.clear()),
+                    _ => {
+                        timer.pop(timer.now().get() + (rng.next_u32() % 3) as u64);
+                    }
+                }
+                let expect = timer.slot.values().map(|node| node.el.el.deadline).min();
+                assert_eq!(timer.next_expiry().map(Tick::get), expect);
+                let now = timer.now().get() + (rng.next_u32() % 5) as u64;
+                assert_eq!(timer.has_due(now), expect.is_some_and(|d| d <= now));
+            }
+        }
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        for i in 0..40 {
+            timer.push((i % 20) as usize, i);
+        }
+        let k = timer.push(3, 40);
+        timer.advance_to(Tick(10));
+        assert_eq!(timer.now().get(), 10);
+        assert_eq!(timer.ready_len(), 23);
+        assert_eq!(timer.cancel(k), Some(40));
+        timer.push(0, 41);
+        let mut vec = Vec::new();
+        while let Some((_, v)) = timer.pop_ready() {
+            vec.push(v);
+        }
+        let mut expect: Vec<_> = (0..11).flat_map(|i| [i, i + 20]).collect();
+        expect.push(41);
+        assert_eq!(vec, expect);
+        assert_eq!(timer.now().get(), 10);
+        // 直接滚动经过的任务不会被跳过
+        timer.roll();
+        timer.roll();
+        assert_eq!(timer.pop(11), Some(11));
+        assert_eq!(timer.pop(11), Some(31));
+        assert_eq!(timer.pop(12), Some(12));
+    }
+
+    #[test]
+    fn test_roll_n() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        for i in 0..40 {
+            timer.push(i as usize, i);
+        }
+        let r = timer.roll_n(5);
+        // 超时时间为0的任务放入时已在就绪队列中
+        assert_eq!(r, RollReport { due: 5, cascaded: 0, wrapped: false });
+        let r = timer.roll_n(20);
+        assert_eq!(r.due, 20);
+        assert_eq!(r.cascaded, 16);
+        assert!(r.wrapped);
+        assert_eq!(timer.ready_len(), 26);
+        assert_eq!(timer.pop_ready().map(|r| r.1), Some(0));
+        assert_eq!(timer.roll_n(0), RollReport::default());
+    }
+
+    #[test]
+    fn test_lazy_restamp() {
+        let mut eager: Timer<u32, 4, 4, 1> = Default::default();
+        let mut lazy: Timer<u32, 4, 4, 1> = Default::default();
+        lazy.set_lazy_restamp(true);
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(42);
+        let mut keys = Vec::new();
+        for now in 0..2000u64 {
+            for _ in 0..(rng.next_u32() % 3) {
+                let t = (rng.next_u32() % 200) as usize;
+                keys.push((eager.push(t, now as u32), lazy.push(t, now as u32)));
+            }
+            if rng.next_u32() % 4 == 0 && !keys.is_empty() {
+                let (a, b) = keys.swap_remove(rng.next_u32() as usize % keys.len());
+                assert_eq!(eager.cancel(a), lazy.cancel(b));
+            }
+            if now == 1000 {
+                lazy.set_lazy_restamp(false);
+                lazy.set_lazy_restamp(true);
+            }
+            loop {
+                let r = eager.pop_at(Tick(now));
+                assert_eq!(r.map(|r| r.1), lazy.pop_at(Tick(now)).map(|r| r.1));
+                if r.is_none() {
+                    break;
+                }
+            }
+        }
+        assert_eq!(eager.len(), lazy.len());
+    }
+
+    #[test]
+    fn test_sparse() {
+        let mut dense: Timer<u32, 4, 4, 1> = Default::default();
+        let mut sparse: Timer<u32, 4, 4, 1> = Default::default();
+        sparse.set_sparse_threshold(8);
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(153);
+        let mut keys = Vec::new();
+        let mut used = 0;
+        for i in 0..3000 {
+            used += !sparse.sparse.is_empty() as usize;
+            // 任务数量在阈值上下来回变化
+            if rng.next_u32() % 3 == 0 || i % 500 < 250 {
+                let t = (rng.next_u32() % 300) as usize;
+                keys.push((dense.push(t, i), sparse.push(t, i)));
+            }
+            match rng.next_u32() % 5 {
+                0 if !keys.is_empty() => {
+                    let (a, b) = keys.swap_remove(rng.next_u32() as usize % keys.len());
+                    assert_eq!(dense.cancel(a), sparse.cancel(b));
+                }
+                1 if !keys.is_empty() => {
+                    let (a, b) = keys[rng.next_u32() as usize % keys.len()];
+                    let t = (rng.next_u32() % 100) as usize;
+                    assert_eq!(dense.reschedule(a, t), sparse.reschedule(b, t));
+                }
+                _ => (),
+            }
+            let now = dense.now().get() + (rng.next_u32() % 20) as u64;
+            loop {
+                let r = dense.pop(now);
+                assert_eq!(r, sparse.pop(now));
+                if r.is_none() {
+                    break;
+                }
+            }
+            assert_eq!(dense.now().get(), sparse.now().get());
+            assert_eq!(dense.len(), sparse.len());
+        }
+        assert!(used > 100, "{}", used);
+    }
+
+    #[test]
+    fn test_on_cancel() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+        let count = Arc::new(AtomicUsize::new(0));
+        let hook = |c: &Arc<AtomicUsize>| {
+            let c = c.clone();
+            move |_: TimerKey, el: &mut u32| {
+                c.fetch_add(1, Ordering::SeqCst);
+                *el += 100;
+            }
+        };
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let a = timer.push_with_cancel(5, 1, hook(&count));
+        timer.push_with_cancel(1, 2, hook(&count));
+        let c = timer.push(30, 3);
+        assert!(timer.set_on_cancel(c, hook(&count)));
+        assert_eq!(timer.cancel(a), Some(101));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        // 到期弹出不调用
+        assert_eq!(timer.pop(1), Some(2));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        // 分离和合并会转移清理函数
+        let (mut other, _) = timer.split_off(|_, _| true);
+        assert!(timer.is_empty());
+        timer.merge(mem::take(&mut other));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        timer.push_with_cancel(10, 4, hook(&count));
+        timer.clear();
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        assert!(timer.is_empty());
+        timer.push_with_cancel(10, 5, hook(&count));
+        drop(timer);
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    #[cfg(not(feature = "minimal"))]
+    fn test_on_roll() {
+        use std::sync::{Arc, Mutex};
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let l = log.clone();
+        timer.set_on_roll(move |n, r| l.lock().unwrap().push((n, r)));
+        timer.push(30, 0);
+        timer.push(0, 1);
+        timer.roll();
+        timer.roll_n(20);
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 21);
+        assert_eq!(log[0], (1, RollReport { due: 0, cascaded: 0, wrapped: false }));
+        assert!(log.iter().all(|(_, r)| r.due == 0 || r.due == 1));
+        assert_eq!(log.iter().filter(|(_, r)| r.wrapped).count(), 1);
+        assert_eq!(log.iter().map(|(_, r)| r.cascaded).sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let mut keys = Vec::new();
+        for i in 0..1000 {
+            keys.push(timer.push((i % 50) as usize, i));
+        }
+        // 取消大部分任务，留下空洞
+        for (i, k) in keys.iter().enumerate() {
+            if i % 10 != 0 {
+                timer.cancel(*k);
+            }
         }
+        timer.roll_n(7);
+        let mut map = Vec::new();
+        timer.compact(|old, new| map.push((old, new)));
+        assert_eq!(map.len(), 100);
+        assert!(timer.capacity() < 200);
+        let mut vec = Vec::new();
+        while let Some((k, v)) = timer.pop_at(Tick(100)) {
+            assert_eq!(map.iter().find(|m| m.1 == k).unwrap().0, keys[v as usize]);
+            vec.push(v);
+        }
+        let mut expect: Vec<_> = (0..100).map(|i| i * 10).collect();
+        expect.sort_by_key(|v| (v % 50, *v));
+        assert_eq!(vec, expect);
+    }
+
+    #[test]
+    #[cfg(not(feature = "minimal"))]
+    fn test_cancel_count() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let a = timer.push(3, 0);
+        let b = timer.push(10, 1);
+        let c = timer.push(100, 2);
+        let d = timer.push(1, 3);
+        timer.advance_to(Tick(2));
+        for k in [a, b, c, d] {
+            timer.cancel(k);
+        }
+        timer.cancel(c);
+        assert_eq!(timer.wheel_cancel_count(), 3);
+        assert_eq!(timer.heap_cancel_count(), 1);
+        assert_eq!(timer.cancelled_count(), 4);
+    }
+
+    #[test]
+    #[cfg(not(feature = "minimal"))]
+    fn test_late() {
+        use std::sync::{Arc, Mutex};
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut timer: Timer<u32, 8, 8, 1> = Default::default();
+        timer.set_late_threshold(50);
+        let l = log.clone();
+        timer.set_on_late(move |_, late| l.lock().unwrap().push(late));
+        timer.push(10, 0);
+        timer.push(20, 1);
+        timer.push(100, 2);
+        assert_eq!(timer.pop(30), Some(0));
+        assert_eq!(timer.pop(80), Some(1));
+        timer.advance_to(Tick(200));
+        assert_eq!(timer.pop_ready().map(|(_, el)| el), Some(2));
+        assert_eq!(timer.late_count(), 2);
+        assert_eq!(*log.lock().unwrap(), [60, 100]);
+    }
+
+    #[test]
+    fn test_dump_dot() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.push(1, 0);
+        timer.push(2, 1);
+        timer.push(2, 2);
+        timer.push(9, 3);
+        timer.push(100, 4);
+        let dot = timer.dump_dot();
+        assert!(dot.starts_with("digraph timer {"));
+        assert!(dot.contains("layer0 [label=\"layer0 x1|<s0> *0: 0|<s1> 1: 1|<s2> 2: 2\"];"));
+        assert!(dot.contains("layer1 [label=\"layer1 x4|<s0> *0: 0|<s2> 2: 1\"];"));
+        assert!(dot.contains("heap [label=\"heap: 1\"];"));
+        assert!(dot.contains("heap -> layer1 -> layer0 -> ready;"));
+    }
+
+    #[test]
+    fn test_report() {
+        let mut timer: Timer<&str, 4, 4, 1> = Default::default();
+        timer.push(100, "far");
+        timer.push(9, "mid");
+        let key = timer.push(2, "near");
+        timer.push(0, "now");
+        let report = timer.report(3);
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("deadline") && lines[0].ends_with("payload"));
+        assert!(lines[1].contains("ready") && lines[1].ends_with("\"now\""));
+        assert!(lines[2].contains(&format!("{:?}", key)));
+        assert!(lines[2].contains("L0/2") && lines[2].ends_with("\"near\""));
+        assert!(lines[3].contains("L1/2") && lines[3].ends_with("\"mid\""));
+        assert!(timer.report(10).lines().last().unwrap().contains("heap"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let build = || {
+            let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+            let keys: Vec<_> = (0..4).map(|i| timer.push(i as usize * 10, i)).collect();
+            (timer, keys)
+        };
+        let (mut left, keys) = build();
+        let (right, _) = build();
+        assert!(left.diff(&right).is_empty());
+        assert_eq!(left.diff(&right).to_string(), "no difference");
+        let right = right.reconfigure::<8, 8, 1>();
+        assert!(left.diff(&right).is_empty());
+        left.cancel(keys[1]);
+        left.reschedule(keys[2], 5);
+        let (mut right, _) = build();
+        let extra = right.push(7, 9);
+        let diff = left.diff(&right);
+        assert_eq!(diff.only_left, []);
+        assert_eq!(diff.only_right, [(keys[1], 10), (extra, 7)]);
+        assert_eq!(diff.deadline_changed, [(keys[2], 5, 20)]);
+        let text = diff.to_string();
+        assert!(text.contains("only in right:") && text.contains("deadline changed:"));
+        assert!(!text.contains("only in left"));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        // 钩子函数不影响定时器的Send和Sync
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+        assert_send::<Timer<u32, 4, 4, 1>>();
+        assert_sync::<Timer<u32, 4, 4, 1>>();
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let cell = std::cell::Cell::new(0);
+        timer.push(1, 1);
+        timer.set_on_drop(move |_, el| cell.set(el));
+        let cell = std::cell::Cell::new(0);
+        let k = timer.push(2, 2);
+        timer.set_on_cancel(k, move |_, el| cell.set(*el));
+        // 多个线程同时通过只读查询回填最近到期时间的缓存
+        std::thread::scope(|s| {
+            s.spawn(|| assert_eq!(timer.next_expiry().map(Tick::get), Some(1)));
+            s.spawn(|| assert_eq!(timer.len(), 2));
+        });
+    }
+
+    #[test]
+    fn test_eq() {
+        let mut left: Timer<&str, 4, 4, 1> = Default::default();
+        let mut right: Timer<&str, 8, 8, 2> = Default::default();
+        left.push(3, "a");
+        left.push(3, "b");
+        left.push(100, "c");
+        // 放入顺序和关键字不同，逻辑内容相同
+        let k = right.push(50, "x");
+        right.push(100, "c");
+        right.push(3, "b");
+        right.push(3, "a");
+        assert!(left != right);
+        right.cancel(k);
+        assert!(left == right);
+        let mut other: Timer<&str, 4, 4, 1> = Default::default();
+        other.push(3, "a");
+        other.push(3, "a");
+        other.push(100, "c");
+        assert!(other != right);
+        other.roll();
+        let mut again: Timer<&str, 4, 4, 1> = Default::default();
+        again.roll();
+        again.push(2, "a");
+        again.push(2, "a");
+        again.push(99, "c");
+        assert!(other == again);
+        // 到期时间不同的元素不能混入同一组
+        let mut left: Timer<&str, 4, 4, 1> = Default::default();
+        left.push(1, "a");
+        left.push(3, "b");
+        left.push(3, "b");
+        let mut right: Timer<&str, 4, 4, 1> = Default::default();
+        right.push(1, "a");
+        right.push(2, "b");
+        right.push(3, "b");
+        assert!(left != right);
+        assert!(right != left);
+    }
+
+    #[test]
+    fn test_inline_overflow() {
+        // 前INLINE个溢出任务内联存放，其余放入堆
+        let mut timer: Timer<usize, 4, 4, 1> = Default::default();
+        let keys: Vec<_> = (0..10).map(|i| timer.push(100 - i * 5, i)).collect();
+        assert_eq!(timer.heap_len(), 10);
+        assert_eq!(timer.heap.len(), 10 - INLINE);
+        // 取消内联的和堆上的任务
+        assert_eq!(timer.cancel(keys[1]), Some(1));
+        assert_eq!(timer.cancel(keys[8]), Some(8));
+        assert_eq!(timer.heap_len(), 8);
+        timer.push(30, 10);
+        assert_eq!(timer.heap.len(), 10 - INLINE - 1);
+        let mut popped = Vec::new();
+        while let Some(el) = timer.pop(200) {
+            popped.push((timer.now().get(), el));
+        }
+        assert_eq!(
+            popped,
+            [(30, 10), (55, 9), (65, 7), (70, 6), (75, 5), (80, 4), (85, 3), (90, 2), (100, 0)]
+        );
+        assert_eq!(timer.heap_len(), 0);
+    }
+
+    #[test]
+    #[cfg(not(feature = "minimal"))]
+    fn test_heap_watermark() {
+        use std::sync::{Arc, Mutex};
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.set_heap_watermark(2);
+        let l = log.clone();
+        timer.set_on_overflow_pressure(move |len| l.lock().unwrap().push(len));
+        let k = timer.push(100, 0);
+        timer.push(5, 1);
+        timer.push(200, 2);
+        timer.push(300, 3);
+        assert_eq!(*log.lock().unwrap(), [2]);
+        timer.cancel(k);
+        timer.push(400, 4);
+        assert_eq!(*log.lock().unwrap(), [2]);
+        timer.set_heap_watermark(4);
+        timer.push(500, 5);
+        assert_eq!(*log.lock().unwrap(), [2, 4]);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.set_overflow(Overflow::Clamp);
+        timer.push(100, 0);
+        assert_eq!(timer.heap_len(), 0);
+        assert_eq!(timer.next_expiry().map(Tick::get), Some(15));
+        timer.set_overflow(Overflow::Reject);
+        assert_eq!(timer.try_push(16, 1).map_err(|e| e.into_inner()), Err(1));
+        assert!(timer.try_push(15, 2).is_ok());
+        timer.push(16, 3);
+        assert_eq!(timer.heap_len(), 1);
+        let mut r = Vec::new();
+        while let Some(el) = timer.pop(16) {
+            r.push(el);
+        }
+        assert_eq!(r, [0, 2, 3]);
+    }
+
+    #[test]
+    fn test_too_large() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let max = timer.max_timeout();
+        assert!(max < usize::MAX);
+        assert!(matches!(timer.try_push(max + 1, 0), Err(PushError::TooLarge(0))));
+        assert!(timer.try_push(max, 1).is_ok());
+        assert!(timer.try_push_time(u64::MAX, 2).is_err());
+        assert!(timer.try_push_time(20, 3).is_ok());
+        assert_eq!(timer.pop(20), Some(3));
+    }
+
+    #[test]
+    fn test_max_push_after_roll() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        for _ in 0..10 {
+            timer.roll();
+        }
+        let max = timer.max_timeout();
+        // 不可失败的放入截断到最大超时时间，到期时间不溢出
+        let k = timer.push(usize::MAX, 0);
+        timer.push(1, 1);
+        assert_eq!(timer.next_expiry().map(Tick::get), Some(11));
+        assert_eq!(timer.pop(100), Some(1));
+        assert_eq!(timer.pop(100), None);
+        assert_eq!(timer.next_expiry().map(Tick::get), Some(10 + max as u64));
+        assert_eq!(timer.cancel(k), Some(0));
+        // 合并窗口向后取整也不溢出
+        timer.set_coalesce(8);
+        timer.push(usize::MAX, 2);
+        let k = timer.push(3, 3);
+        timer.reschedule(k, usize::MAX);
+        assert!(timer.next_expiry().map(Tick::get).is_some());
+        assert_eq!(timer.len(), 2);
+    }
+
+    #[test]
+    fn test_immediate() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.set_sparse_threshold(8);
+        timer.push(3, 0);
+        timer.push(0, 1);
+        timer.push_at(Tick(0), 2);
+        assert_eq!(timer.ready_len(), 2);
+        assert_eq!(timer.pop(0), Some(1));
+        assert_eq!(timer.pop(0), Some(2));
+        assert_eq!(timer.pop(0), None);
+        assert_eq!(timer.now().get(), 0);
+        assert_eq!(timer.pop(3), Some(0));
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.push(1, 0);
+        timer.warm_up(64);
+        let cap = timer.capacity();
+        assert!(cap >= 64);
+        assert_eq!(timer.len(), 1);
+        #[cfg(not(feature = "minimal"))]
+        assert_eq!(timer.add_count(), 1);
+        for i in 1..64 {
+            timer.push(i as usize * 7, i);
+        }
+        assert_eq!(timer.capacity(), cap);
+        assert_eq!(timer.pop(1), Some(0));
     }
 
+    #[test]
+    fn test_reuse() {
+        let mut timer: Timer<usize, 4, 4, 1> = Timer::with_capacity(100);
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(55555);
+        let mut keys: Vec<_> = (0..100).map(|i| timer.push(i % 40, i)).collect();
+        let capacity = timer.capacity();
+        for i in 0..10000 {
+            let j = rng.next_u32() as usize % keys.len();
+            timer.cancel(keys[j]);
+            keys[j] = timer.push((rng.next_u32() % 40) as usize, i);
+            timer.roll();
+        }
+        assert_eq!(timer.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_requeue() {
+        let mut timer: Timer<usize, 4, 4, 1> = Default::default();
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(44444);
+        let keys: Vec<_> = (0..100)
+            .map(|i| timer.push((rng.next_u32() % 30) as usize, i))
+            .collect();
+        for now in 0..20 {
+            while let Some((key, el)) = timer.peek_kv(now) {
+                let el = *el;
+                assert!(timer.reschedule(key, (rng.next_u32() % 30) as usize));
+                assert_eq!(keys[el], key);
+            }
+        }
+        let mut vec: Vec<_> = timer.into_iter().map(|(key, _, el)| (key, el)).collect();
+        vec.sort_unstable_by_key(|e| e.1);
+        assert_eq!(vec, keys.into_iter().zip(0..100).collect::<Vec<_>>());
+        // 弹出后按原关键字放回，只有窗口内最近弹出的关键字可以放回
+        let mut timer: Timer<usize, 4, 4, 1> = Default::default();
+        timer.set_requeue_window(2);
+        let keys: Vec<_> = (0..4).map(|i| timer.push(1, i)).collect();
+        let popped: Vec<_> = std::iter::from_fn(|| timer.pop_at(Tick(1))).collect();
+        assert_eq!(popped.iter().map(|p| p.0).collect::<Vec<_>>(), keys);
+        assert_eq!(timer.requeue(keys[1], 1, 5), Err(PushError::Stale(1)));
+        assert_eq!(timer.requeue(keys[3], 3, 5), Ok(()));
+        assert_eq!(timer.requeue(keys[3], 3, 5), Err(PushError::Stale(3)));
+        assert_eq!(timer.peek_kv(6), Some((keys[3], &3)));
+        // 弹出后的槽位不复用，新任务拿到新的关键字
+        let other = timer.push(1, 9);
+        assert!(!keys.contains(&other));
+        assert_eq!(timer.cancel(keys[3]), Some(3));
+        // 与try_push相同的检查，失败时关键字仍可放回
+        timer.set_max_pending(1);
+        assert_eq!(timer.requeue(keys[2], 2, 5), Err(PushError::Full(2)));
+        timer.set_max_pending(usize::MAX);
+        timer.set_overflow(Overflow::Reject);
+        assert_eq!(timer.requeue(keys[2], 2, 100), Err(PushError::Overflow(2)));
+        timer.shutdown(0);
+        assert_eq!(timer.requeue(keys[2], 2, 5), Err(PushError::Shutdown(2)));
+        assert!(timer.is_empty());
+        // 默认不保留，弹出后无法放回
+        let mut timer: Timer<usize, 4, 4, 1> = Default::default();
+        let key = timer.push(0, 0);
+        assert_eq!(timer.pop_at(Tick(0)), Some((key, 0)));
+        assert_eq!(timer.requeue(key, 0, 1), Err(PushError::Stale(0)));
+        // 缩小窗口后，之后的弹出回收超出的槽位
+        let mut timer: Timer<usize, 4, 4, 1> = Default::default();
+        timer.set_requeue_window(8);
+        let keys: Vec<_> = (0..8).map(|i| timer.push(0, i)).collect();
+        while timer.pop(0).is_some() {}
+        timer.set_requeue_window(1);
+        let last = timer.push(0, 8);
+        timer.pop(0);
+        assert!(keys.iter().all(|k| timer.requeue(*k, 0, 1).is_err()));
+        assert_eq!(timer.requeue(last, 8, 1), Ok(()));
+    }
+
+    #[test]
+    fn test_split_merge() {
+        let mut timer: Timer<usize, 4, 4, 1> = Default::default();
+        for i in 0..40 {
+            timer.push(i, i);
+        }
+        while timer.pop(3).is_some() {}
+        let (mut odd, map) = timer.split_off(|_, el| el % 2 == 1);
+        assert_eq!(map.len(), 18);
+        assert_eq!(odd.now().get(), timer.now().get());
+        assert_eq!(odd.pop(5), Some(5));
+        #[cfg(not(feature = "minimal"))]
+        assert_eq!(timer.cancelled_count(), 0);
+        timer.merge(odd);
+        let mut timer: Timer<usize, 8, 2, 2> = timer.reconfigure();
+        let mut vec = vec![];
+        for now in 0..40 {
+            while let Some(el) = timer.pop(now) {
+                vec.push((now, el));
+            }
+        }
+        let expect: Vec<_> = (4..40).filter(|i| *i != 5).map(|i| (i as u64, i)).collect();
+        assert_eq!(vec, expect);
+        // 合并保留原到期时间，不受目标的合并窗口和截断影响，已过期的任务按到期时间插入就绪队列
+        let mut target: Timer<u64, 4, 4, 1> = Default::default();
+        target.set_coalesce(10);
+        target.set_overflow(Overflow::Clamp);
+        target.push(0, 0);
+        target.push(3, 10);
+        for _ in 0..8 {
+            target.roll();
+        }
+        let mut other: Timer<u64, 4, 4, 1> = Default::default();
+        for deadline in [5, 9, 13, 200] {
+            other.push(deadline as usize, deadline);
+        }
+        target.merge_from(other);
+        assert_eq!(target.next_expiry().map(Tick::get), Some(0));
+        let order: Vec<_> = std::iter::from_fn(|| target.pop_expired(u64::MAX >> 1))
+            .take(6)
+            .map(|e| (e.deadline, e.el))
+            .collect();
+        assert_eq!(order, [(0, 0), (5, 5), (9, 9), (10, 10), (13, 13), (200, 200)]);
+        // 分离的定时器继承设置，清理函数随任务转移而不被调用
+        let mut timer: Timer<u64, 4, 4, 1> = Default::default();
+        timer.set_coalesce(4);
+        let k = timer.push(6, 6);
+        let called = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let c = called.clone();
+        timer.set_on_cancel(k, move |_, _| c.store(true, AtomicOrdering::Relaxed));
+        let (mut split, map) = timer.split_off(|_, _| true);
+        assert!(timer.is_empty());
+        assert!(!called.load(AtomicOrdering::Relaxed));
+        assert_eq!(split.coalesce(), 4);
+        split.push(1, 1);
+        assert_eq!(split.next_expiry().map(Tick::get), Some(4));
+        split.cancel(map[0].1);
+        assert!(called.load(AtomicOrdering::Relaxed));
+    }
 }
@@ -0,0 +1,666 @@
+//! 超出定时轮的任务的存储，默认为二叉堆，可通过特性换成BTreeMap、配对堆或多叉堆，
+//! 同时开启多个时按 btree、pairing、dary8、dary4 的顺序选择
+//!
+//! * overflow_btree: 按 `(绝对时间, 关键字)` 有序存放，重新修正时间时整体重建
+//! * overflow_pairing: 配对堆，放入和合并为O(1)，节点在数组中的位置不随调整变化
+//! * overflow_dary4 / overflow_dary8: 4叉或8叉堆，层数更少，下沉时比较的子节点在同一缓存行内
+//!
+//! 配对堆和BTreeMap在每圈修正时间时需要遍历或重建；各存储在不同任务数量下的吞吐量用
+//! `cargo bench --bench overflow --features <特性>` 对比
+
+use std::fmt;
+
+#[cfg(not(any(
+    feature = "overflow_btree",
+    feature = "overflow_pairing",
+    feature = "overflow_dary4",
+    feature = "overflow_dary8"
+)))]
+use std::cmp::Reverse;
+
+#[cfg(not(any(
+    feature = "overflow_btree",
+    feature = "overflow_pairing",
+    feature = "overflow_dary4",
+    feature = "overflow_dary8"
+)))]
+use pi_ext_heap::ExtHeap;
+use pi_slot_deque::Slot;
+use pi_slot_wheel::{TimeoutItem, TimerKey};
+#[cfg(not(any(
+    feature = "overflow_btree",
+    feature = "overflow_dary4",
+    feature = "overflow_dary8"
+)))]
+use slotmap::Key;
+
+use crate::{node_mut, Entry, INLINE};
+
+/// 定时任务的节点存储
+pub(crate) type Nodes<T> = Slot<TimerKey, TimeoutItem<Entry<T>>>;
+
+/// 当前选择的溢出存储
+#[cfg(feature = "overflow_btree")]
+pub(crate) type Store = BTreeStore;
+#[cfg(all(feature = "overflow_pairing", not(feature = "overflow_btree")))]
+pub(crate) type Store = PairingStore;
+#[cfg(all(
+    any(feature = "overflow_dary4", feature = "overflow_dary8"),
+    not(any(feature = "overflow_btree", feature = "overflow_pairing"))
+))]
+pub(crate) type Store = DaryStore<
+    {
+        if cfg!(feature = "overflow_dary8") {
+            8
+        } else {
+            4
+        }
+    },
+>;
+#[cfg(not(any(
+    feature = "overflow_btree",
+    feature = "overflow_pairing",
+    feature = "overflow_dary4",
+    feature = "overflow_dary8"
+)))]
+pub(crate) type Store = HeapStore;
+
+/// 溢出存储，元素为 `(绝对时间, 关键字)`，按绝对时间取出最小的任务
+/// * @tip 存储负责维护节点的位置标记，位置标记从 `base::<N0, N, L>()` 开始
+pub(crate) trait OverflowStore: Default + fmt::Debug {
+    /// 任务数量
+    fn len(&self) -> usize;
+    /// 查看最早的任务
+    fn peek(&self) -> Option<(usize, TimerKey)>;
+    /// 放入任务，设置节点的位置标记
+    fn push<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        timeout: usize,
+        key: TimerKey,
+        slot: &mut Nodes<T>,
+    );
+    /// 取出最早的任务
+    fn pop<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        slot: &mut Nodes<T>,
+    ) -> Option<(usize, TimerKey)>;
+    /// 移除任务，loc为位置标记减去起点
+    fn remove<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        loc: usize,
+        key: TimerKey,
+        slot: &mut Nodes<T>,
+    );
+    /// 将全部任务的时间减去指定值，统一减去相同的值不会改变先后顺序
+    fn restamp<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        delta: usize,
+        slot: &mut Nodes<T>,
+    );
+    /// 预分配容量
+    fn warm_up(&mut self, capacity: usize);
+    /// 清空
+    fn clear(&mut self);
+}
+
+/// 溢出存储的位置标记的起点，排在定时轮和内联数组之后
+#[inline]
+pub(crate) const fn base<const N0: usize, const N: usize, const L: usize>() -> usize {
+    N0 + N * L + INLINE
+}
+
+/// 时间减去指定值，堆上的任务至少在一圈之后到期，出现下溢说明内部状态已不一致
+#[inline]
+pub(crate) fn sub(timeout: usize, delta: usize) -> usize {
+    #[cfg(feature = "strict")]
+    return timeout
+        .checked_sub(delta)
+        .unwrap_or_else(|| panic!("heap timeout {} underflow, delta: {}", timeout, delta));
+    #[cfg(not(feature = "strict"))]
+    {
+        debug_assert!(
+            timeout >= delta,
+            "heap timeout {} underflow, delta: {}",
+            timeout,
+            delta
+        );
+        timeout.saturating_sub(delta)
+    }
+}
+
+/// 二叉堆
+#[cfg(not(any(
+    feature = "overflow_btree",
+    feature = "overflow_pairing",
+    feature = "overflow_dary4",
+    feature = "overflow_dary8"
+)))]
+#[derive(Debug, Default)]
+pub(crate) struct HeapStore(ExtHeap<Reverse<(usize, TimerKey)>>);
+
+#[cfg(not(any(
+    feature = "overflow_btree",
+    feature = "overflow_pairing",
+    feature = "overflow_dary4",
+    feature = "overflow_dary8"
+)))]
+fn set_index<T, const N0: usize, const N: usize, const L: usize>(
+    slot: &mut Nodes<T>,
+    arr: &mut [Reverse<(usize, TimerKey)>],
+    loc: usize,
+) {
+    node_mut(slot, arr[loc].0 .1).el.index = base::<N0, N, L>() + loc;
+}
+
+#[cfg(not(any(
+    feature = "overflow_btree",
+    feature = "overflow_pairing",
+    feature = "overflow_dary4",
+    feature = "overflow_dary8"
+)))]
+impl OverflowStore for HeapStore {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn peek(&self) -> Option<(usize, TimerKey)> {
+        self.0.peek().map(|it| it.0)
+    }
+    fn push<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        timeout: usize,
+        key: TimerKey,
+        slot: &mut Nodes<T>,
+    ) {
+        self.0
+            .push(Reverse((timeout, key)), slot, set_index::<T, N0, N, L>);
+    }
+    fn pop<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        slot: &mut Nodes<T>,
+    ) -> Option<(usize, TimerKey)> {
+        self.0.pop(slot, set_index::<T, N0, N, L>).map(|it| it.0)
+    }
+    fn remove<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        loc: usize,
+        _key: TimerKey,
+        slot: &mut Nodes<T>,
+    ) {
+        self.0.remove(loc, slot, set_index::<T, N0, N, L>);
+        // 堆的删除只会向下调整，换到删除位置的末尾元素可能需要向上调整
+        if loc < self.0.len() {
+            self.0.repair(
+                loc,
+                std::cmp::Ordering::Greater,
+                slot,
+                set_index::<T, N0, N, L>,
+            );
+        }
+    }
+    fn restamp<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        delta: usize,
+        #[cfg_attr(not(feature = "strict"), allow(unused_variables))] slot: &mut Nodes<T>,
+    ) {
+        #[cfg(not(feature = "strict"))]
+        for i in 0..self.0.len() {
+            let timeout = unsafe { &mut self.0.get_unchecked_mut(i).0 .0 };
+            *timeout = sub(*timeout, delta);
+        }
+        #[cfg(feature = "strict")]
+        {
+            let mut vec = std::mem::take(&mut self.0).into_vec();
+            for it in vec.iter_mut() {
+                it.0 .0 = sub(it.0 .0, delta);
+            }
+            self.0 = vec.into();
+            // 重建堆后重新设置全部任务的堆位置
+            for (loc, it) in self.0.as_slice().iter().enumerate() {
+                node_mut(slot, it.0 .1).el.index = base::<N0, N, L>() + loc;
+            }
+        }
+    }
+    fn warm_up(&mut self, capacity: usize) {
+        // 空的堆写满占位元素后清空，保留已写入的容量
+        self.0.reserve(capacity.saturating_sub(self.0.len()));
+        if self.0.is_empty() {
+            for _ in 0..capacity {
+                self.0
+                    .push(Reverse((0, TimerKey::null())), &mut (), pi_ext_heap::empty);
+            }
+            self.0.clear();
+        }
+    }
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// 按 `(绝对时间, 关键字)` 排序的BTreeSet，节点的超时时间字段记录绝对时间，用于移除时定位
+#[cfg(feature = "overflow_btree")]
+#[derive(Debug, Default)]
+pub(crate) struct BTreeStore(std::collections::BTreeSet<(usize, TimerKey)>);
+
+#[cfg(feature = "overflow_btree")]
+impl OverflowStore for BTreeStore {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn peek(&self) -> Option<(usize, TimerKey)> {
+        self.0.first().copied()
+    }
+    fn push<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        timeout: usize,
+        key: TimerKey,
+        slot: &mut Nodes<T>,
+    ) {
+        let node = node_mut(slot, key);
+        node.el.index = base::<N0, N, L>();
+        node.el.timeout = timeout;
+        self.0.insert((timeout, key));
+    }
+    fn pop<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        _slot: &mut Nodes<T>,
+    ) -> Option<(usize, TimerKey)> {
+        self.0.pop_first()
+    }
+    fn remove<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        _loc: usize,
+        key: TimerKey,
+        slot: &mut Nodes<T>,
+    ) {
+        let timeout = node_mut(slot, key).el.timeout;
+        self.0.remove(&(timeout, key));
+    }
+    fn restamp<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        delta: usize,
+        slot: &mut Nodes<T>,
+    ) {
+        // 顺序不变，按顺序重建
+        self.0 = std::mem::take(&mut self.0)
+            .into_iter()
+            .map(|(timeout, key)| {
+                let timeout = sub(timeout, delta);
+                node_mut(slot, key).el.timeout = timeout;
+                (timeout, key)
+            })
+            .collect();
+    }
+    fn warm_up(&mut self, _capacity: usize) {}
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// 配对堆的空指针
+#[cfg(all(feature = "overflow_pairing", not(feature = "overflow_btree")))]
+const NIL: usize = usize::MAX;
+
+/// 配对堆的节点，prev指向父节点（首个子节点时）或左兄弟
+#[cfg(all(feature = "overflow_pairing", not(feature = "overflow_btree")))]
+#[derive(Debug, Clone, Copy)]
+struct PairingNode {
+    timeout: usize,
+    key: TimerKey,
+    child: usize,
+    sibling: usize,
+    prev: usize,
+}
+
+/// 配对堆，节点存放在数组中，位置在节点生命周期内不变，移除的位置通过空闲列表复用
+#[cfg(all(feature = "overflow_pairing", not(feature = "overflow_btree")))]
+#[derive(Debug)]
+pub(crate) struct PairingStore {
+    nodes: Vec<PairingNode>,
+    free: Vec<usize>,
+    root: usize,
+    len: usize,
+}
+
+#[cfg(all(feature = "overflow_pairing", not(feature = "overflow_btree")))]
+impl Default for PairingStore {
+    fn default() -> Self {
+        PairingStore {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NIL,
+            len: 0,
+        }
+    }
+}
+
+#[cfg(all(feature = "overflow_pairing", not(feature = "overflow_btree")))]
+impl PairingStore {
+    #[inline]
+    fn less(&self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.nodes[a], &self.nodes[b]);
+        (a.timeout, a.key) <= (b.timeout, b.key)
+    }
+    /// 合并两个独立的堆，返回新的根
+    fn meld(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+        let (root, child) = if self.less(a, b) { (a, b) } else { (b, a) };
+        let first = self.nodes[root].child;
+        self.nodes[child].sibling = first;
+        self.nodes[child].prev = root;
+        if first != NIL {
+            self.nodes[first].prev = child;
+        }
+        self.nodes[root].child = child;
+        self.nodes[root].prev = NIL;
+        self.nodes[root].sibling = NIL;
+        root
+    }
+    /// 两趟合并兄弟链表，返回新的根
+    /// * @tip 第一趟从左到右两两合并，结果通过sibling串成栈，第二趟从右到左依次合并，不分配内存
+    fn merge_pairs(&mut self, first: usize) -> usize {
+        let mut stack = NIL;
+        let mut cur = first;
+        while cur != NIL {
+            let a = cur;
+            let b = self.nodes[a].sibling;
+            cur = if b == NIL { NIL } else { self.nodes[b].sibling };
+            self.detach(a);
+            let head = if b == NIL {
+                a
+            } else {
+                self.detach(b);
+                self.meld(a, b)
+            };
+            self.nodes[head].sibling = stack;
+            stack = head;
+        }
+        let mut root = NIL;
+        while stack != NIL {
+            let head = stack;
+            stack = self.nodes[head].sibling;
+            self.nodes[head].sibling = NIL;
+            root = self.meld(head, root);
+        }
+        root
+    }
+    fn detach(&mut self, i: usize) {
+        self.nodes[i].sibling = NIL;
+        self.nodes[i].prev = NIL;
+    }
+    /// 从父节点或左兄弟上剪下子树
+    fn cut(&mut self, i: usize) {
+        let prev = self.nodes[i].prev;
+        let sibling = self.nodes[i].sibling;
+        if self.nodes[prev].child == i {
+            self.nodes[prev].child = sibling;
+        } else {
+            self.nodes[prev].sibling = sibling;
+        }
+        if sibling != NIL {
+            self.nodes[sibling].prev = prev;
+        }
+        self.detach(i);
+    }
+    fn release(&mut self, i: usize) -> (usize, TimerKey) {
+        self.free.push(i);
+        self.len -= 1;
+        let node = &mut self.nodes[i];
+        (
+            node.timeout,
+            std::mem::replace(&mut node.key, TimerKey::null()),
+        )
+    }
+}
+
+#[cfg(all(feature = "overflow_pairing", not(feature = "overflow_btree")))]
+impl OverflowStore for PairingStore {
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn peek(&self) -> Option<(usize, TimerKey)> {
+        (self.root != NIL).then(|| (self.nodes[self.root].timeout, self.nodes[self.root].key))
+    }
+    fn push<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        timeout: usize,
+        key: TimerKey,
+        slot: &mut Nodes<T>,
+    ) {
+        let node = PairingNode {
+            timeout,
+            key,
+            child: NIL,
+            sibling: NIL,
+            prev: NIL,
+        };
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = node;
+                i
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        node_mut(slot, key).el.index = base::<N0, N, L>() + i;
+        self.len += 1;
+        self.root = self.meld(self.root, i);
+    }
+    fn pop<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        _slot: &mut Nodes<T>,
+    ) -> Option<(usize, TimerKey)> {
+        if self.root == NIL {
+            return None;
+        }
+        let root = self.root;
+        self.root = self.merge_pairs(self.nodes[root].child);
+        Some(self.release(root))
+    }
+    fn remove<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        loc: usize,
+        _key: TimerKey,
+        _slot: &mut Nodes<T>,
+    ) {
+        if loc == self.root {
+            self.root = self.merge_pairs(self.nodes[loc].child);
+        } else {
+            self.cut(loc);
+            let sub = self.merge_pairs(self.nodes[loc].child);
+            self.root = self.meld(self.root, sub);
+        }
+        self.release(loc);
+    }
+    fn restamp<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        delta: usize,
+        _slot: &mut Nodes<T>,
+    ) {
+        // 空闲的节点关键字为空，跳过
+        for node in self.nodes.iter_mut().filter(|node| !node.key.is_null()) {
+            node.timeout = sub(node.timeout, delta);
+        }
+    }
+    fn warm_up(&mut self, capacity: usize) {
+        self.nodes
+            .reserve(capacity.saturating_sub(self.nodes.len()));
+    }
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.root = NIL;
+        self.len = 0;
+    }
+}
+
+/// D叉堆，元素直接存放在数组中，调整时更新节点的位置标记
+#[cfg(all(
+    any(feature = "overflow_dary4", feature = "overflow_dary8"),
+    not(any(feature = "overflow_btree", feature = "overflow_pairing"))
+))]
+#[derive(Debug, Default)]
+pub(crate) struct DaryStore<const D: usize>(Vec<(usize, TimerKey)>);
+
+#[cfg(all(
+    any(feature = "overflow_dary4", feature = "overflow_dary8"),
+    not(any(feature = "overflow_btree", feature = "overflow_pairing"))
+))]
+impl<const D: usize> DaryStore<D> {
+    #[inline]
+    fn set<T, const N0: usize, const N: usize, const L: usize>(
+        &self,
+        i: usize,
+        slot: &mut Nodes<T>,
+    ) {
+        node_mut(slot, self.0[i].1).el.index = base::<N0, N, L>() + i;
+    }
+    fn sift_up<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        mut i: usize,
+        slot: &mut Nodes<T>,
+    ) {
+        while i > 0 {
+            let parent = (i - 1) / D;
+            if self.0[parent] <= self.0[i] {
+                break;
+            }
+            self.0.swap(i, parent);
+            self.set::<T, N0, N, L>(i, slot);
+            i = parent;
+        }
+        self.set::<T, N0, N, L>(i, slot);
+    }
+    fn sift_down<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        mut i: usize,
+        slot: &mut Nodes<T>,
+    ) {
+        let len = self.0.len();
+        loop {
+            let first = i * D + 1;
+            if first >= len {
+                break;
+            }
+            let mut min = first;
+            for c in first + 1..(first + D).min(len) {
+                if self.0[c] < self.0[min] {
+                    min = c;
+                }
+            }
+            if self.0[i] <= self.0[min] {
+                break;
+            }
+            self.0.swap(i, min);
+            self.set::<T, N0, N, L>(i, slot);
+            i = min;
+        }
+        self.set::<T, N0, N, L>(i, slot);
+    }
+}
+
+#[cfg(all(
+    any(feature = "overflow_dary4", feature = "overflow_dary8"),
+    not(any(feature = "overflow_btree", feature = "overflow_pairing"))
+))]
+impl<const D: usize> OverflowStore for DaryStore<D> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn peek(&self) -> Option<(usize, TimerKey)> {
+        self.0.first().copied()
+    }
+    fn push<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        timeout: usize,
+        key: TimerKey,
+        slot: &mut Nodes<T>,
+    ) {
+        self.0.push((timeout, key));
+        self.sift_up::<T, N0, N, L>(self.0.len() - 1, slot);
+    }
+    fn pop<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        slot: &mut Nodes<T>,
+    ) -> Option<(usize, TimerKey)> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let it = self.0.swap_remove(0);
+        if !self.0.is_empty() {
+            self.sift_down::<T, N0, N, L>(0, slot);
+        }
+        Some(it)
+    }
+    fn remove<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        loc: usize,
+        _key: TimerKey,
+        slot: &mut Nodes<T>,
+    ) {
+        self.0.swap_remove(loc);
+        if loc >= self.0.len() {
+            return;
+        }
+        // 换到删除位置的末尾元素可能需要向上或向下调整
+        if loc > 0 && self.0[loc] < self.0[(loc - 1) / D] {
+            self.sift_up::<T, N0, N, L>(loc, slot);
+        } else {
+            self.sift_down::<T, N0, N, L>(loc, slot);
+        }
+    }
+    fn restamp<T, const N0: usize, const N: usize, const L: usize>(
+        &mut self,
+        delta: usize,
+        _slot: &mut Nodes<T>,
+    ) {
+        for it in self.0.iter_mut() {
+            it.0 = sub(it.0, delta);
+        }
+    }
+    fn warm_up(&mut self, capacity: usize) {
+        self.0.reserve(capacity.saturating_sub(self.0.len()));
+    }
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::{RngCore, SeedableRng};
+
+    use crate::Timer;
+
+    #[test]
+    fn test_overflow_store() {
+        // 大部分任务超出定时轮，随机取消一半，按当前特性选择的溢出存储运行
+        let mut timer: Timer<u64, 4, 4, 1> = Default::default();
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(44444);
+        let mut keys = Vec::new();
+        for i in 0..2000u64 {
+            let timeout = (rng.next_u32() % 5000) as usize;
+            keys.push((timer.push(timeout, i), timeout as u64, i));
+        }
+        let mut expect = Vec::new();
+        for (j, (key, deadline, i)) in keys.into_iter().enumerate() {
+            if j % 2 == 0 {
+                assert_eq!(timer.cancel(key), Some(i));
+            } else {
+                expect.push((deadline, i));
+            }
+        }
+        expect.sort_unstable();
+        let mut popped = Vec::new();
+        while let Some(el) = timer.pop(5000) {
+            popped.push((timer.now().get(), el));
+        }
+        assert_eq!(popped, expect);
+    }
+}
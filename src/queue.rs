@@ -0,0 +1,150 @@
+//! 到期队列，驱动线程将到期任务批量移入队列，多个工作线程并行取出
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{tick::Tick, Timer};
+
+struct Inner<T> {
+    queue: Mutex<(VecDeque<(TimerKey, T)>, bool)>,
+    cond: Condvar,
+}
+
+/// 多生产者多消费者的到期队列，克隆后指向同一个队列
+pub struct ExpiredQueue<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for ExpiredQueue<T> {
+    fn clone(&self) -> Self {
+        ExpiredQueue {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Default for ExpiredQueue<T> {
+    fn default() -> Self {
+        ExpiredQueue {
+            inner: Arc::new(Inner {
+                queue: Mutex::new((VecDeque::new(), false)),
+                cond: Condvar::new(),
+            }),
+        }
+    }
+}
+
+impl<T> ExpiredQueue<T> {
+    fn lock(&self) -> MutexGuard<'_, (VecDeque<(TimerKey, T)>, bool)> {
+        self.inner.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// 弹出定时器中指定时间内全部到期的任务，一次加锁批量放入队列，并唤醒等待的消费者
+    /// * @return `usize` 放入的任务数量
+    pub fn fill<const N0: usize, const N: usize, const L: usize>(
+        &self,
+        timer: &mut Timer<T, N0, N, L>,
+        now: u64,
+    ) -> usize {
+        let mut batch = Vec::new();
+        while let Some(it) = timer.pop_at(Tick(now)) {
+            batch.push(it);
+        }
+        let count = batch.len();
+        if count > 0 {
+            self.lock().0.extend(batch);
+            if count == 1 {
+                self.inner.cond.notify_one();
+            } else {
+                self.inner.cond.notify_all();
+            }
+        }
+        count
+    }
+    /// 取出一个到期任务，不等待
+    pub fn pop(&self) -> Option<(TimerKey, T)> {
+        self.lock().0.pop_front()
+    }
+    /// 取出一个到期任务，队列为空时最多等待指定时间
+    /// * @return 超时或队列已关闭且为空时返回None
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<(TimerKey, T)> {
+        let guard = self.lock();
+        let (mut guard, _) = self
+            .inner
+            .cond
+            .wait_timeout_while(guard, timeout, |q| q.0.is_empty() && !q.1)
+            .unwrap_or_else(|e| e.into_inner());
+        guard.0.pop_front()
+    }
+    /// 取出一个到期任务，队列为空时一直等待
+    /// * @return 队列已关闭且为空时返回None
+    pub fn pop_wait(&self) -> Option<(TimerKey, T)> {
+        let guard = self.lock();
+        let mut guard = self
+            .inner
+            .cond
+            .wait_while(guard, |q| q.0.is_empty() && !q.1)
+            .unwrap_or_else(|e| e.into_inner());
+        guard.0.pop_front()
+    }
+    /// 关闭队列，唤醒全部等待的消费者，队列中剩余的任务仍可取出
+    pub fn close(&self) {
+        self.lock().1 = true;
+        self.inner.cond.notify_all();
+    }
+    /// 队列是否已关闭
+    pub fn is_closed(&self) -> bool {
+        self.lock().1
+    }
+    /// 获得队列中的任务数量
+    pub fn len(&self) -> usize {
+        self.lock().0.len()
+    }
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.lock().0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let queue: ExpiredQueue<u32> = Default::default();
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let q = queue.clone();
+                std::thread::spawn(move || {
+                    let mut r = Vec::new();
+                    while let Some((_, v)) = q.pop_wait() {
+                        r.push(v);
+                    }
+                    r
+                })
+            })
+            .collect();
+        let mut timer: Timer<u32, 8, 8, 1> = Default::default();
+        for i in 0..1000 {
+            timer.push((i % 100) as usize, i);
+        }
+        let mut count = 0;
+        for now in 0..100 {
+            count += queue.fill(&mut timer, now);
+        }
+        assert_eq!(count, 1000);
+        queue.close();
+        let mut all: Vec<_> = workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..1000).collect::<Vec<_>>());
+        assert!(queue.pop().is_none());
+    }
+}
@@ -0,0 +1,310 @@
+//! 操作记录器，记录定时器上的每个操作及参数，可通过回放重建完全相同的定时器状态
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{tick::Tick, Overflow, PushError, Rounding, Timer};
+
+/// 一条操作记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record<T> {
+    /// 放入定时任务，key为放入时返回的键，回放时用于校验
+    Push {
+        timeout: usize,
+        el: T,
+        key: TimerKey,
+    },
+    /// 按指定的取整方式放入定时任务
+    PushWithRounding {
+        timeout: usize,
+        el: T,
+        rounding: Rounding,
+        key: TimerKey,
+    },
+    /// 按绝对时间放入定时任务
+    PushTime { time: u64, el: T, key: TimerKey },
+    /// 将未弹出的定时任务按新的超时时间重新定时
+    Reschedule { key: TimerKey, timeout: usize },
+    /// 将已弹出的定时任务按原关键字放回
+    Requeue {
+        key: TimerKey,
+        el: T,
+        timeout: usize,
+    },
+    /// 取消定时任务
+    Cancel { key: TimerKey },
+    /// 滚动一次
+    Roll,
+    /// 弹出指定时间内的一个定时任务
+    Pop { now: u64 },
+    /// 滚动到指定时间，到期任务移入就绪队列
+    Advance { now: u64 },
+    /// 从就绪队列弹出一个定时任务
+    PopReady,
+    /// 设置滚动次数
+    SetRollCount { roll_count: u64 },
+    /// 修改设置
+    Set(Setting),
+}
+
+/// 一次设置的修改
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    /// `set_coalesce`
+    Coalesce(u64),
+    /// `set_rounding`
+    Rounding(Rounding),
+    /// `set_overflow`
+    Overflow(Overflow),
+    /// `set_never_early`
+    NeverEarly(bool),
+    /// `set_lazy_restamp`
+    LazyRestamp(bool),
+    /// `set_sparse_threshold`
+    SparseThreshold(usize),
+    /// `set_max_pending`
+    MaxPending(usize),
+    /// `set_requeue_window`
+    RequeueWindow(usize),
+}
+
+impl Setting {
+    fn apply<T, const N0: usize, const N: usize, const L: usize>(
+        self,
+        timer: &mut Timer<T, N0, N, L>,
+    ) {
+        match self {
+            Setting::Coalesce(window) => timer.set_coalesce(window),
+            Setting::Rounding(rounding) => timer.set_rounding(rounding),
+            Setting::Overflow(overflow) => timer.set_overflow(overflow),
+            Setting::NeverEarly(never_early) => timer.set_never_early(never_early),
+            Setting::LazyRestamp(lazy) => timer.set_lazy_restamp(lazy),
+            Setting::SparseThreshold(threshold) => timer.set_sparse_threshold(threshold),
+            Setting::MaxPending(max) => timer.set_max_pending(max),
+            Setting::RequeueWindow(window) => timer.set_requeue_window(window),
+        }
+    }
+}
+
+/// 记录操作的定时器
+pub struct Recorder<T: Clone, const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<T, N0, N, L>,
+    log: Vec<Record<T>>,
+}
+
+impl<T: Clone, const N0: usize, const N: usize, const L: usize> Default for Recorder<T, N0, N, L> {
+    fn default() -> Self {
+        Recorder {
+            timer: Default::default(),
+            log: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone, const N0: usize, const N: usize, const L: usize> Recorder<T, N0, N, L> {
+    /// 获得内部的定时器
+    pub fn timer(&self) -> &Timer<T, N0, N, L> {
+        &self.timer
+    }
+    /// 获得操作记录
+    pub fn log(&self) -> &[Record<T>] {
+        &self.log
+    }
+    /// 取出操作记录，并清空
+    pub fn take_log(&mut self) -> Vec<Record<T>> {
+        std::mem::take(&mut self.log)
+    }
+    /// 放入一个定时任务
+    pub fn push(&mut self, timeout: usize, el: T) -> TimerKey {
+        let key = self.timer.push(timeout, el.clone());
+        self.log.push(Record::Push { timeout, el, key });
+        key
+    }
+    /// 按指定的取整方式放入一个定时任务
+    pub fn push_with_rounding(&mut self, timeout: usize, el: T, rounding: Rounding) -> TimerKey {
+        let key = self.timer.push_with_rounding(timeout, el.clone(), rounding);
+        self.log.push(Record::PushWithRounding {
+            timeout,
+            el,
+            rounding,
+            key,
+        });
+        key
+    }
+    /// 按绝对时间放入一个定时任务
+    pub fn push_time(&mut self, time: u64, el: T) -> TimerKey {
+        let key = self.timer.push_at(Tick(time), el.clone());
+        self.log.push(Record::PushTime { time, el, key });
+        key
+    }
+    /// 将未弹出的定时任务按新的超时时间重新定时
+    pub fn reschedule(&mut self, key: TimerKey, timeout: usize) -> bool {
+        self.log.push(Record::Reschedule { key, timeout });
+        self.timer.reschedule(key, timeout)
+    }
+    /// 将已弹出的定时任务按原关键字放回
+    pub fn requeue(&mut self, key: TimerKey, el: T, timeout: usize) -> Result<(), PushError<T>> {
+        self.log.push(Record::Requeue {
+            key,
+            el: el.clone(),
+            timeout,
+        });
+        self.timer.requeue(key, el, timeout)
+    }
+    /// 取消定时任务
+    pub fn cancel(&mut self, key: TimerKey) -> Option<T> {
+        self.log.push(Record::Cancel { key });
+        self.timer.cancel(key)
+    }
+    /// 滚动一次
+    pub fn roll(&mut self) {
+        self.log.push(Record::Roll);
+        self.timer.roll()
+    }
+    /// 弹出指定时间内的一个定时任务
+    pub fn pop(&mut self, now: u64) -> Option<T> {
+        self.log.push(Record::Pop { now });
+        self.timer.pop(now)
+    }
+    /// 弹出指定时间内的一个定时任务及其键
+    pub fn pop_kv(&mut self, now: u64) -> Option<(TimerKey, T)> {
+        self.log.push(Record::Pop { now });
+        self.timer.pop_at(Tick(now))
+    }
+    /// 滚动到指定时间，将经过的全部到期任务移入就绪队列
+    pub fn advance(&mut self, now: u64) {
+        self.log.push(Record::Advance { now });
+        self.timer.advance_to(Tick(now))
+    }
+    /// 从就绪队列弹出一个定时任务
+    pub fn pop_ready(&mut self) -> Option<(TimerKey, T)> {
+        self.log.push(Record::PopReady);
+        self.timer.pop_ready()
+    }
+    /// 设置滚动次数，只能在没有定时任务时设置
+    pub fn set_roll_count(&mut self, roll_count: u64) -> bool {
+        self.log.push(Record::SetRollCount { roll_count });
+        self.timer.set_roll_count(roll_count)
+    }
+    /// 修改设置
+    pub fn set(&mut self, setting: Setting) {
+        self.log.push(Record::Set(setting));
+        setting.apply(&mut self.timer)
+    }
+}
+
+/// 按操作记录回放，重建定时器
+/// * @tip 键的分配是确定的，回放时放入返回的键和记录不一致会panic
+pub fn replay<T: Clone, const N0: usize, const N: usize, const L: usize>(
+    log: &[Record<T>],
+) -> Timer<T, N0, N, L> {
+    let mut timer: Timer<T, N0, N, L> = Default::default();
+    for (i, r) in log.iter().enumerate() {
+        match r {
+            Record::Push { timeout, el, key } => {
+                let k = timer.push(*timeout, el.clone());
+                assert_eq!(k, *key, "replay diverged at record {}", i);
+            }
+            Record::PushWithRounding {
+                timeout,
+                el,
+                rounding,
+                key,
+            } => {
+                let k = timer.push_with_rounding(*timeout, el.clone(), *rounding);
+                assert_eq!(k, *key, "replay diverged at record {}", i);
+            }
+            Record::PushTime { time, el, key } => {
+                let k = timer.push_at(Tick(*time), el.clone());
+                assert_eq!(k, *key, "replay diverged at record {}", i);
+            }
+            Record::Reschedule { key, timeout } => {
+                timer.reschedule(*key, *timeout);
+            }
+            Record::Requeue { key, el, timeout } => {
+                let _ = timer.requeue(*key, el.clone(), *timeout);
+            }
+            Record::Cancel { key } => {
+                timer.cancel(*key);
+            }
+            Record::Roll => timer.roll(),
+            Record::Pop { now } => {
+                timer.pop(*now);
+            }
+            Record::Advance { now } => timer.advance_to(Tick(*now)),
+            Record::PopReady => {
+                timer.pop_ready();
+            }
+            Record::SetRollCount { roll_count } => {
+                timer.set_roll_count(*roll_count);
+            }
+            Record::Set(setting) => setting.apply(&mut timer),
+        }
+    }
+    timer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::{RngCore, SeedableRng};
+
+    #[test]
+    fn test_replay() {
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(1234);
+        let mut rec: Recorder<u32, 4, 4, 2> = Default::default();
+        rec.set_roll_count(7);
+        rec.set(Setting::LazyRestamp(true));
+        rec.set(Setting::MaxPending(1000));
+        rec.set(Setting::RequeueWindow(4));
+        let mut keys = Vec::new();
+        for i in 0..4000 {
+            match rng.next_u32() % 12 {
+                0 | 1 => keys.push(rec.push((rng.next_u32() % 100) as usize, i)),
+                2 => {
+                    let time = rec.timer().now().get() + (rng.next_u32() % 100) as u64;
+                    keys.push(rec.push_time(time, i));
+                }
+                3 => {
+                    let rounding = [Rounding::Floor, Rounding::Ceil, Rounding::Nearest]
+                        [rng.next_u32() as usize % 3];
+                    let timeout = (rng.next_u32() % 100) as usize;
+                    keys.push(rec.push_with_rounding(timeout, i, rounding));
+                }
+                4 if !keys.is_empty() => {
+                    let k = keys.swap_remove(rng.next_u32() as usize % keys.len());
+                    rec.cancel(k);
+                }
+                5 if !keys.is_empty() => {
+                    let k = keys[rng.next_u32() as usize % keys.len()];
+                    rec.reschedule(k, (rng.next_u32() % 100) as usize);
+                }
+                6 => {
+                    rec.advance(rec.timer().now().get() + (rng.next_u32() % 4) as u64);
+                    while rec.pop_ready().is_some() {}
+                }
+                7 => rec.set(match rng.next_u32() % 4 {
+                    0 => Setting::Coalesce(1 + (rng.next_u32() % 4) as u64),
+                    1 => Setting::Overflow(Overflow::Clamp),
+                    2 => Setting::NeverEarly(rng.next_u32() % 2 == 0),
+                    _ => Setting::SparseThreshold((rng.next_u32() % 8) as usize),
+                }),
+                _ => {
+                    let now = rec.timer().now().get() + (rng.next_u32() % 4) as u64;
+                    if let Some((k, el)) = rec.pop_kv(now) {
+                        if rng.next_u32() % 4 == 0 {
+                            let _ = rec.requeue(k, el, (rng.next_u32() % 10) as usize);
+                        }
+                    }
+                }
+            }
+        }
+        let mut timer: Timer<u32, 4, 4, 2> = replay(rec.log());
+        assert_eq!(timer.len(), rec.timer().len());
+        assert_eq!(timer.now().get(), rec.timer().now().get());
+        let now = timer.now().get() + 1000;
+        while let Some((k, v)) = timer.pop_at(Tick(now)) {
+            assert_eq!(rec.pop_kv(now), Some((k, v)));
+        }
+        assert!(rec.timer().is_empty());
+    }
+}
@@ -0,0 +1,441 @@
+//! 周期定时器，周期任务每次到期后按周期重新定时，关键字在整个生命周期内不变
+//! 重新定时可以从原定的到期时间开始（固定频率，没有累积漂移），也可以从弹出时开始（固定延迟）
+//! 可以限制到期次数和最后到期时间，达到后自动移除并报告完成，与取消区分
+//! 连续多次延迟到期时按失误策略处理，如升级告警、放宽周期或自动取消，避免健康检查等周期任务悄悄退化
+
+use slotmap::SlotMap;
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{clock::CatchUp, tick::Tick, Timer};
+
+/// 重新定时的起点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pace {
+    /// 从原定的到期时间开始，到期时间始终是首次到期时间加周期的整数倍，落后时连续到期追赶
+    FixedRate,
+    /// 从弹出时开始，负载高时到期时间逐次推迟
+    #[default]
+    FixedDelay,
+}
+
+/// 周期任务的配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence {
+    /// 周期，即两次到期之间的滚动次数
+    pub period: usize,
+    /// 重新定时的起点
+    pub pace: Pace,
+    /// 最多到期的次数，None为不限
+    pub count: Option<u32>,
+    /// 最后到期时间，下一次到期晚于该时间时不再重新定时，None为不限
+    pub until: Option<u64>,
+}
+
+impl Recurrence {
+    /// 固定频率的周期任务
+    pub fn fixed_rate(period: usize) -> Self {
+        Recurrence {
+            period,
+            pace: Pace::FixedRate,
+            count: None,
+            until: None,
+        }
+    }
+    /// 固定延迟的周期任务
+    pub fn fixed_delay(period: usize) -> Self {
+        Recurrence {
+            period,
+            pace: Pace::FixedDelay,
+            count: None,
+            until: None,
+        }
+    }
+}
+
+/// 周期任务弹出后的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// 已重新定时
+    Rearmed,
+    /// 达到到期次数或最后到期时间，已移除
+    Completed,
+    /// 按失误策略自动取消，已移除
+    Cancelled,
+}
+
+/// 连续失误达到上限时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissAction {
+    /// 只调用失误回调
+    Escalate,
+    /// 周期乘以指定倍数
+    Widen(usize),
+    /// 本次到期交付后自动取消
+    Cancel,
+}
+
+/// 失误策略，弹出时延迟超过容忍的滚动次数记为一次失误，按时到期时连续失误清零
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissPolicy {
+    /// 容忍的延迟滚动次数
+    pub tolerance: u64,
+    /// 连续失误的上限，达到时执行处理方式并重新计数
+    pub limit: u32,
+    /// 处理方式
+    pub action: MissAction,
+}
+
+/// 失误回调，参数为关键字、连续失误次数和处理方式
+pub type OnMiss = Box<dyn FnMut(TimerKey, u32, MissAction) + Send>;
+
+/// 周期任务
+struct Recur<T> {
+    el: T,
+    period: usize,
+    pace: Pace,
+    count: Option<u32>,
+    until: Option<u64>,
+    /// 已到期的次数
+    fires: u32,
+    /// 内部定时器上的关键字
+    inner: TimerKey,
+    /// 连续失误次数
+    misses: u32,
+}
+
+/// 周期定时器
+pub struct RecurringTimer<T, const N0: usize, const N: usize, const L: usize> {
+    /// 定时元素为对外的关键字
+    timer: Timer<TimerKey, N0, N, L>,
+    entries: SlotMap<TimerKey, Recur<T>>,
+    policy: Option<MissPolicy>,
+    on_miss: Option<OnMiss>,
+    /// 固定频率的周期任务落后时的追赶策略
+    catch_up: CatchUp,
+    /// 按追赶策略跳过的到期次数
+    skipped: u64,
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Default for RecurringTimer<T, N0, N, L> {
+    fn default() -> Self {
+        RecurringTimer {
+            timer: Default::default(),
+            entries: SlotMap::with_key(),
+            policy: None,
+            on_miss: None,
+            catch_up: CatchUp::Burst,
+            skipped: 0,
+        }
+    }
+}
+
+impl<T: Clone, const N0: usize, const N: usize, const L: usize> RecurringTimer<T, N0, N, L> {
+    /// 获得内部的定时器，定时元素为对外的关键字
+    pub fn timer(&self) -> &Timer<TimerKey, N0, N, L> {
+        &self.timer
+    }
+    /// 设置失误策略，None为不检查失误，默认不检查
+    pub fn set_miss_policy(&mut self, policy: Option<MissPolicy>) {
+        if let Some(policy) = policy {
+            assert!(policy.limit > 0, "miss limit must be positive");
+        }
+        self.policy = policy;
+    }
+    /// 获得失误策略
+    pub fn miss_policy(&self) -> Option<MissPolicy> {
+        self.policy
+    }
+    /// 设置固定频率的周期任务落后时的追赶策略，默认逐次交付全部落后的到期
+    /// * @tip Coalesce在一次延迟交付后把下一次到期推到now之后，Skip跳过落后now超过max_lag的到期；
+    ///   跳过的到期不计入到期次数，固定延迟的周期任务本身不会积压，不受影响
+    pub fn set_catch_up(&mut self, catch_up: CatchUp) {
+        self.catch_up = catch_up;
+    }
+    /// 获得追赶策略
+    pub fn catch_up(&self) -> CatchUp {
+        self.catch_up
+    }
+    /// 获得按追赶策略跳过的到期次数
+    pub fn skipped_count(&self) -> u64 {
+        self.skipped
+    }
+    /// 设置连续失误达到上限时调用的回调，任何处理方式都会调用
+    pub fn set_on_miss<F: FnMut(TimerKey, u32, MissAction) + Send + 'static>(&mut self, f: F) {
+        self.on_miss = Some(Box::new(f));
+    }
+    /// 放入一个固定延迟的周期任务，经过一个周期后第一次到期
+    pub fn push(&mut self, period: usize, el: T) -> TimerKey {
+        self.push_with(Recurrence::fixed_delay(period), el)
+    }
+    /// 按配置放入一个周期任务，经过一个周期后第一次到期
+    /// * @tip 到期次数不能为0，最后到期时间不能早于第一次到期的时间
+    pub fn push_with(&mut self, recurrence: Recurrence, el: T) -> TimerKey {
+        assert!(recurrence.period > 0, "period must be positive");
+        assert!(recurrence.count != Some(0), "count must be positive");
+        let first = self.timer.now().get() + recurrence.period as u64;
+        assert!(
+            recurrence.until.is_none_or(|until| until >= first),
+            "until is before the first fire"
+        );
+        let key = self.entries.insert(Recur {
+            el,
+            period: recurrence.period,
+            pace: recurrence.pace,
+            count: recurrence.count,
+            until: recurrence.until,
+            fires: 0,
+            inner: TimerKey::default(),
+            misses: 0,
+        });
+        self.entries[key].inner = self.timer.push(recurrence.period, key);
+        key
+    }
+    /// 取消周期任务
+    pub fn cancel(&mut self, key: TimerKey) -> Option<T> {
+        let recur = self.entries.remove(key)?;
+        self.timer.cancel(recur.inner);
+        Some(recur.el)
+    }
+    /// 获得周期任务的周期
+    pub fn period(&self, key: TimerKey) -> Option<usize> {
+        self.entries.get(key).map(|r| r.period)
+    }
+    /// 获得周期任务重新定时的起点
+    pub fn pace(&self, key: TimerKey) -> Option<Pace> {
+        self.entries.get(key).map(|r| r.pace)
+    }
+    /// 获得周期任务已到期的次数
+    pub fn fires(&self, key: TimerKey) -> Option<u32> {
+        self.entries.get(key).map(|r| r.fires)
+    }
+    /// 获得周期任务的连续失误次数
+    pub fn misses(&self, key: TimerKey) -> Option<u32> {
+        self.entries.get(key).map(|r| r.misses)
+    }
+    /// 弹出指定时间内到期的一个周期任务，并按周期重新定时，固定频率从到期时间开始，固定延迟从now开始
+    /// * @tip 自动取消或完成的任务在本次弹出后不再到期，返回的是任务本身而不是克隆
+    pub fn pop(&mut self, now: u64) -> Option<(TimerKey, T)> {
+        self.pop_outcome(now).map(|(key, el, _)| (key, el))
+    }
+    /// 弹出指定时间内到期的一个周期任务，并返回弹出后的状态，用于区分完成和按失误策略取消
+    pub fn pop_outcome(&mut self, now: u64) -> Option<(TimerKey, T, Outcome)> {
+        let e = self.timer.pop_expired(now)?;
+        let key = e.el;
+        let late = now.saturating_sub(e.deadline);
+        let recur = &mut self.entries[key];
+        recur.fires = recur.fires.saturating_add(1);
+        let mut action = None;
+        if let Some(policy) = self.policy {
+            if late > policy.tolerance {
+                recur.misses += 1;
+                if recur.misses >= policy.limit {
+                    action = Some(policy.action);
+                }
+            } else {
+                recur.misses = 0;
+            }
+        }
+        if let Some(action) = action {
+            if let Some(f) = &mut self.on_miss {
+                f(key, recur.misses, action);
+            }
+            recur.misses = 0;
+            match action {
+                MissAction::Escalate => (),
+                MissAction::Widen(factor) => recur.period = recur.period.saturating_mul(factor),
+                MissAction::Cancel => {
+                    return self
+                        .entries
+                        .remove(key)
+                        .map(|r| (key, r.el, Outcome::Cancelled));
+                }
+            }
+        }
+        let from = match recur.pace {
+            Pace::FixedRate => e.deadline,
+            Pace::FixedDelay => now,
+        };
+        let mut next = from + recur.period as u64;
+        if recur.pace == Pace::FixedRate {
+            // 跳过落后的到期，保持原来的节奏
+            let floor = match self.catch_up {
+                CatchUp::Burst => None,
+                CatchUp::Coalesce => Some(now + 1),
+                CatchUp::Skip { max_lag } => Some(now.saturating_sub(max_lag)),
+            };
+            if let Some(floor) = floor.filter(|floor| next < *floor) {
+                let period = recur.period as u64;
+                let missed = (floor - next).div_ceil(period);
+                next += missed * period;
+                self.skipped += missed;
+            }
+        }
+        if recur.count.is_some_and(|count| recur.fires >= count)
+            || recur.until.is_some_and(|until| next > until)
+        {
+            return self
+                .entries
+                .remove(key)
+                .map(|r| (key, r.el, Outcome::Completed));
+        }
+        recur.inner = self.timer.push_at(Tick(next), key);
+        Some((key, recur.el.clone(), Outcome::Rearmed))
+    }
+    /// 获得周期任务的数量
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    /// 是否没有周期任务
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recurring_miss() {
+        use std::sync::{Arc, Mutex};
+        let mut timer: RecurringTimer<&str, 8, 8, 1> = Default::default();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = log.clone();
+        timer.set_on_miss(move |_, misses, action| l.lock().unwrap().push((misses, action)));
+        timer.set_miss_policy(Some(MissPolicy {
+            tolerance: 1,
+            limit: 2,
+            action: MissAction::Widen(2),
+        }));
+        let check = timer.push(4, "check");
+        // 按时到期，下一次在8
+        assert_eq!(timer.pop(4), Some((check, "check")));
+        assert_eq!(timer.pop(7), None);
+        // 连续两次延迟超过1次滚动，周期加倍
+        assert_eq!(timer.pop(10), Some((check, "check")));
+        assert_eq!(timer.misses(check), Some(1));
+        assert_eq!(timer.pop(20), Some((check, "check")));
+        assert_eq!(timer.period(check), Some(8));
+        assert_eq!(timer.misses(check), Some(0));
+        assert_eq!(*log.lock().unwrap(), [(2, MissAction::Widen(2))]);
+        assert_eq!(timer.pop(27), None);
+        assert_eq!(timer.pop(28), Some((check, "check")));
+        timer.set_miss_policy(Some(MissPolicy {
+            tolerance: 0,
+            limit: 1,
+            action: MissAction::Cancel,
+        }));
+        assert_eq!(timer.pop(40), Some((check, "check")));
+        assert!(timer.is_empty());
+        assert!(timer.timer().is_empty());
+        let ping = timer.push(3, "ping");
+        assert_eq!(timer.cancel(ping), Some("ping"));
+        assert_eq!(timer.pop(100), None);
+    }
+
+    #[test]
+    fn test_recurring_pace() {
+        let mut timer: RecurringTimer<&str, 8, 8, 1> = Default::default();
+        let rate = timer.push_with(Recurrence::fixed_rate(10), "rate");
+        let delay = timer.push(10, "delay");
+        assert_eq!(timer.pace(rate), Some(Pace::FixedRate));
+        assert_eq!(timer.pace(delay), Some(Pace::FixedDelay));
+        // 晚3次滚动弹出，固定频率下一次在20，固定延迟在23
+        assert_eq!(timer.pop(13), Some((rate, "rate")));
+        assert_eq!(timer.pop(13), Some((delay, "delay")));
+        assert_eq!(timer.pop(20), Some((rate, "rate")));
+        assert_eq!(timer.pop(22), None);
+        assert_eq!(timer.pop(23), Some((delay, "delay")));
+        // 落后多个周期时固定频率连续到期追赶，之后回到原来的节奏
+        let mut fires = 0;
+        while let Some((key, _)) = timer.pop(55) {
+            if key == rate {
+                fires += 1;
+            }
+        }
+        assert_eq!(fires, 3);
+        assert_eq!(timer.pop(59), None);
+        assert_eq!(timer.pop(60), Some((rate, "rate")));
+    }
+
+    #[test]
+    fn test_recurring_catch_up() {
+        use crate::clock::CatchUp;
+        let mut timer: RecurringTimer<u32, 8, 8, 1> = Default::default();
+        timer.set_catch_up(CatchUp::Coalesce);
+        let keys: Vec<_> = (0..3)
+            .map(|i| timer.push_with(Recurrence::fixed_rate(10), i))
+            .collect();
+        // 落后了100次滚动，每个周期任务只交付一次，之后回到原来的节奏
+        let mut vec: Vec<_> = std::iter::from_fn(|| timer.pop(105)).collect();
+        vec.sort_by_key(|e| e.1);
+        assert_eq!(
+            vec,
+            keys.iter()
+                .zip(0..)
+                .map(|(k, i)| (*k, i))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(timer.skipped_count(), 3 * 9);
+        assert_eq!(timer.pop(109), None);
+        assert_eq!(std::iter::from_fn(|| timer.pop(110)).count(), 3);
+        // 跳过落后超过20次滚动的到期，到期在120、180、190和200
+        timer.set_catch_up(CatchUp::Skip { max_lag: 20 });
+        assert_eq!(std::iter::from_fn(|| timer.pop(200)).count(), 3 * 4);
+        timer.set_catch_up(CatchUp::Burst);
+        assert_eq!(std::iter::from_fn(|| timer.pop(300)).count(), 3 * 10);
+    }
+
+    #[test]
+    fn test_recurring_end() {
+        let mut timer: RecurringTimer<&str, 8, 8, 1> = Default::default();
+        let twice = timer.push_with(
+            Recurrence {
+                count: Some(2),
+                ..Recurrence::fixed_rate(5)
+            },
+            "twice",
+        );
+        let until = timer.push_with(
+            Recurrence {
+                until: Some(20),
+                ..Recurrence::fixed_delay(7)
+            },
+            "until",
+        );
+        assert_eq!(
+            timer.pop_outcome(5),
+            Some((twice, "twice", Outcome::Rearmed))
+        );
+        assert_eq!(timer.fires(twice), Some(1));
+        assert_eq!(
+            timer.pop_outcome(7),
+            Some((until, "until", Outcome::Rearmed))
+        );
+        assert_eq!(
+            timer.pop_outcome(10),
+            Some((twice, "twice", Outcome::Completed))
+        );
+        assert_eq!(timer.fires(twice), None);
+        // 下一次在21，晚于最后到期时间
+        assert_eq!(
+            timer.pop_outcome(14),
+            Some((until, "until", Outcome::Completed))
+        );
+        assert!(timer.is_empty());
+        assert!(timer.timer().is_empty());
+        // 按失误策略取消与完成区分
+        timer.set_miss_policy(Some(MissPolicy {
+            tolerance: 0,
+            limit: 1,
+            action: MissAction::Cancel,
+        }));
+        let late = timer.push(3, "late");
+        assert_eq!(
+            timer.pop_outcome(30),
+            Some((late, "late", Outcome::Cancelled))
+        );
+        assert!(timer.is_empty());
+    }
+}
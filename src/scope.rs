@@ -0,0 +1,102 @@
+//! 定时器作用域，通过作用域句柄放入的定时任务在作用域结束时自动取消，用于请求级的超时，不会遗漏取消
+
+use pi_slot_wheel::TimerKey;
+
+use crate::Timer;
+
+/// 作用域句柄，借用定时器并记录经由它放入的定时任务
+pub struct Scope<'a, T, const N0: usize, const N: usize, const L: usize> {
+    timer: &'a mut Timer<T, N0, N, L>,
+    keys: Vec<TimerKey>,
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Scope<'_, T, N0, N, L> {
+    /// 获得定时器，直接放入的任务不受作用域管理
+    pub fn timer(&mut self) -> &mut Timer<T, N0, N, L> {
+        self.timer
+    }
+    /// 放入一个定时任务，作用域结束时若未到期则自动取消
+    pub fn push(&mut self, timeout: usize, el: T) -> TimerKey {
+        let key = self.timer.push(timeout, el);
+        self.keys.push(key);
+        key
+    }
+    /// 取消定时任务
+    pub fn cancel(&mut self, key: TimerKey) -> Option<T> {
+        self.detach(key);
+        self.timer.cancel(key)
+    }
+    /// 使定时任务脱离作用域，作用域结束后继续保留
+    /// * @return `bool` 任务是否由本作用域管理
+    pub fn detach(&mut self, key: TimerKey) -> bool {
+        match self.keys.iter().position(|k| *k == key) {
+            Some(i) => {
+                self.keys.swap_remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+    /// 获得作用域管理的定时任务数量，包括已到期弹出的任务
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    /// 作用域是否没有管理的定时任务
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Drop for Scope<'_, T, N0, N, L> {
+    fn drop(&mut self) {
+        // 已弹出的任务关键字已失效，取消时直接忽略
+        for key in self.keys.drain(..) {
+            self.timer.cancel(key);
+        }
+    }
+}
+
+/// 作用域接口
+impl<T, const N0: usize, const N: usize, const L: usize> Timer<T, N0, N, L> {
+    /// 在作用域中执行函数，经由作用域句柄放入且未脱离的定时任务在函数返回或panic时自动取消
+    pub fn scope<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Scope<'_, T, N0, N, L>) -> R,
+    {
+        let mut scope = Scope {
+            timer: self,
+            keys: Vec::new(),
+        };
+        f(&mut scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        let kept = timer.scope(|s| {
+            s.push(1, 0);
+            s.push(5, 1);
+            let kept = s.push(6, 2);
+            s.timer().push(7, 3);
+            assert_eq!(s.timer().pop(1), Some(0));
+            assert!(s.detach(kept));
+            assert_eq!(s.len(), 2);
+            kept
+        });
+        assert_eq!(timer.len(), 2);
+        assert_eq!(timer.cancel(kept), Some(2));
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            timer.scope(|s| {
+                s.push(2, 4);
+                panic!("request failed");
+            })
+        }));
+        assert!(r.is_err());
+        assert_eq!(timer.len(), 1);
+    }
+}
@@ -0,0 +1,420 @@
+//! 定时器服务，定时器由独立线程持有，通过命令通道放入和取消定时任务，到期的任务交给输出函数处理
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use pi_slot_wheel::TimerKey;
+use slotmap::SecondaryMap;
+
+use crate::{
+    clock::{Clock, StdClock},
+    tick::Tick,
+    Timer,
+};
+
+/// 取消标记，其他线程设置后服务线程在弹出时丢弃该任务，无需经过命令通道
+pub use crate::cancel::CancelFlag;
+
+/// 带取消标记的任务至少达到这个数量才清理
+const PURGE_MIN: usize = 64;
+
+/// 服务命令
+pub enum Command<T> {
+    /// 放入定时任务，通过reply返回键，带取消标记的任务在弹出和关闭时检查标记，并在放入时定期清理
+    Schedule {
+        timeout: usize,
+        payload: T,
+        flag: Option<CancelFlag>,
+        reply: Option<Sender<TimerKey>>,
+    },
+    /// 放入在时钟指定时刻到期的定时任务，到期时间向上取整到滚动次数，不会早于该时刻交给输出函数
+    ScheduleAt {
+        at: Duration,
+        payload: T,
+        reply: Option<Sender<TimerKey>>,
+    },
+    /// 取消定时任务
+    Cancel { key: TimerKey },
+    /// 关闭服务
+    Shutdown,
+}
+
+/// 服务句柄，可克隆后在其他线程使用
+pub struct ServiceHandle<T> {
+    sender: Sender<Command<T>>,
+    clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl<T> Clone for ServiceHandle<T> {
+    fn clone(&self) -> Self {
+        ServiceHandle {
+            sender: self.sender.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+impl<T> ServiceHandle<T> {
+    /// 发送命令
+    /// * @return 服务已关闭时返回false
+    pub fn send(&self, cmd: Command<T>) -> bool {
+        self.sender.send(cmd).is_ok()
+    }
+    /// 放入定时任务，等待服务返回键
+    /// * @return 服务已关闭时返回None
+    pub fn schedule(&self, timeout: usize, payload: T) -> Option<TimerKey> {
+        let (reply, r) = channel();
+        if !self.send(Command::Schedule {
+            timeout,
+            payload,
+            flag: None,
+            reply: Some(reply),
+        }) {
+            return None;
+        }
+        r.recv().ok()
+    }
+    /// 放入在指定时刻到期的定时任务，等待服务返回键
+    /// * @tip 与 `schedule` 不同，到期时间不受服务线程当前滚动次数落后的影响，不会提前到期
+    /// * @return 服务已关闭时返回None
+    pub fn schedule_at(&self, at: Instant, payload: T) -> Option<TimerKey> {
+        // 先取单调时间再取时钟，换算到时钟上的时刻不会提前
+        let left = at.saturating_duration_since(Instant::now());
+        self.schedule_clock(self.clock.now() + left, payload)
+    }
+    /// 放入经过指定时长后到期的定时任务，按调用时服务时钟的时刻计算到期时刻，等待服务返回键
+    /// * @tip 同 `schedule_at`，不会提前到期
+    /// * @return 服务已关闭时返回None
+    pub fn schedule_after(&self, dur: Duration, payload: T) -> Option<TimerKey> {
+        self.schedule_clock(self.clock.now() + dur, payload)
+    }
+    fn schedule_clock(&self, at: Duration, payload: T) -> Option<TimerKey> {
+        let (reply, r) = channel();
+        if !self.send(Command::ScheduleAt {
+            at,
+            payload,
+            reply: Some(reply),
+        }) {
+            return None;
+        }
+        r.recv().ok()
+    }
+    /// 放入带取消标记的定时任务，不等待服务返回键
+    /// * @tip 任务被标记取消后仍占用服务中的位置，直到到期或服务下次清理时才被丢弃；
+    ///   带取消标记的任务数量比上次清理后翻倍时，服务在放入时清理全部已标记取消的任务
+    /// * @return 服务已关闭时返回None
+    pub fn schedule_cancellable(&self, timeout: usize, payload: T) -> Option<CancelFlag> {
+        let flag = CancelFlag::default();
+        if !self.send(Command::Schedule {
+            timeout,
+            payload,
+            flag: Some(flag.clone()),
+            reply: None,
+        }) {
+            return None;
+        }
+        Some(flag)
+    }
+    /// 取消定时任务
+    /// * @return 服务已关闭时返回false
+    pub fn cancel(&self, key: TimerKey) -> bool {
+        self.send(Command::Cancel { key })
+    }
+}
+
+/// 定时器服务
+pub struct TimerService<T> {
+    handle: ServiceHandle<T>,
+    thread: Option<JoinHandle<Vec<(u64, TimerKey, T)>>>,
+}
+
+impl<T: Send + 'static> TimerService<T> {
+    /// 启动服务线程，每隔tick滚动一次定时器，到期的任务交给sink处理
+    /// * @tip sink在单次处理中panic时捕获并丢弃该任务，服务线程继续运行
+    pub fn spawn<const N0: usize, const N: usize, const L: usize, F>(
+        tick: Duration,
+        sink: F,
+    ) -> Self
+    where
+        F: FnMut(TimerKey, T) + Send + 'static,
+    {
+        Self::spawn_with_clock::<N0, N, L, _, _>(StdClock::default(), tick, sink)
+    }
+    /// 启动服务线程，按指定时钟的时间滚动定时器，其余同 `spawn`
+    /// * @tip 服务线程按真实时间每隔约tick检查一次时钟，时钟为 `ManualClock` 时推进后最多一个tick内交付；
+    ///   句柄与服务线程共享该时钟
+    pub fn spawn_with_clock<const N0: usize, const N: usize, const L: usize, C, F>(
+        clock: C,
+        tick: Duration,
+        mut sink: F,
+    ) -> Self
+    where
+        C: Clock + Send + Sync + 'static,
+        F: FnMut(TimerKey, T) + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        let clock: Arc<dyn Clock + Send + Sync> = Arc::new(clock);
+        let start = clock.now();
+        let handle = ServiceHandle {
+            sender,
+            clock: clock.clone(),
+        };
+        let thread = thread::spawn(move || {
+            let mut timer: Timer<T, N0, N, L> = Default::default();
+            let mut flags = SecondaryMap::new();
+            // 上次清理后剩余的带取消标记的任务数量，翻倍时再清理，均摊到每次放入为O(1)
+            let mut purged = 0;
+            loop {
+                let elapsed = clock.now().saturating_sub(start);
+                let now = (elapsed.as_nanos() / tick.as_nanos().max(1)) as u64;
+                while let Some((key, el)) = timer.pop_at(Tick(now)) {
+                    if !is_cancelled(&mut flags, key) {
+                        deliver(&mut sink, key, el);
+                    }
+                }
+                let next = Duration::from_nanos((tick.as_nanos() as u64).saturating_mul(now + 1));
+                let elapsed = clock.now().saturating_sub(start);
+                match receiver.recv_timeout(next.saturating_sub(elapsed)) {
+                    Ok(Command::Schedule {
+                        timeout,
+                        payload,
+                        flag,
+                        reply,
+                    }) => {
+                        let key = timer.push(timeout, payload);
+                        if let Some(flag) = flag {
+                            flags.insert(key, flag);
+                            if flags.len() >= (purged * 2).max(PURGE_MIN) {
+                                purge(&mut timer, &mut flags);
+                                purged = flags.len();
+                            }
+                        }
+                        if let Some(reply) = reply {
+                            let _ = reply.send(key);
+                        }
+                    }
+                    Ok(Command::ScheduleAt { at, payload, reply }) => {
+                        let at = at.saturating_sub(start).as_nanos();
+                        let deadline = at.div_ceil(tick.as_nanos().max(1)) as u64;
+                        let key = timer.push_at(Tick(deadline), payload);
+                        if let Some(reply) = reply {
+                            let _ = reply.send(key);
+                        }
+                    }
+                    Ok(Command::Cancel { key }) => {
+                        timer.cancel(key);
+                        flags.remove(key);
+                    }
+                    Ok(Command::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
+                        let report = timer.shutdown(timer.now().get());
+                        for (key, el) in report.expired {
+                            if !is_cancelled(&mut flags, key) {
+                                deliver(&mut sink, key, el);
+                            }
+                        }
+                        return report
+                            .pending
+                            .into_iter()
+                            .filter(|it| !is_cancelled(&mut flags, it.1))
+                            .collect();
+                    }
+                    Err(RecvTimeoutError::Timeout) => (),
+                }
+            }
+        });
+        TimerService {
+            handle,
+            thread: Some(thread),
+        }
+    }
+    /// 获得服务句柄
+    pub fn handle(&self) -> ServiceHandle<T> {
+        self.handle.clone()
+    }
+    /// 放入定时任务，等待服务返回键
+    pub fn schedule(&self, timeout: usize, payload: T) -> Option<TimerKey> {
+        self.handle.schedule(timeout, payload)
+    }
+    /// 放入在指定时刻到期的定时任务，等待服务返回键
+    pub fn schedule_at(&self, at: Instant, payload: T) -> Option<TimerKey> {
+        self.handle.schedule_at(at, payload)
+    }
+    /// 放入经过指定时长后到期的定时任务，等待服务返回键
+    pub fn schedule_after(&self, dur: Duration, payload: T) -> Option<TimerKey> {
+        self.handle.schedule_after(dur, payload)
+    }
+    /// 取消定时任务
+    pub fn cancel(&self, key: TimerKey) -> bool {
+        self.handle.cancel(key)
+    }
+    /// 关闭服务，等待服务线程退出
+    /// * @tip 服务线程panic时在调用线程重新抛出
+    /// * @return 尚未到期的定时任务，元素为 `(到期时间, 关键字, 定时元素)`，按到期时间顺序排列
+    pub fn shutdown(mut self) -> Vec<(u64, TimerKey, T)> {
+        self.stop()
+    }
+    fn stop(&mut self) -> Vec<(u64, TimerKey, T)> {
+        self.handle.send(Command::Shutdown);
+        match self.thread.take() {
+            Some(thread) => thread.join().unwrap_or_else(|e| panic::resume_unwind(e)),
+            None => Vec::new(),
+        }
+    }
+}
+
+fn deliver<T, F: FnMut(TimerKey, T)>(sink: &mut F, key: TimerKey, el: T) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| sink(key, el)));
+}
+
+/// 移除任务的取消标记，返回任务是否已标记取消
+fn is_cancelled(flags: &mut SecondaryMap<TimerKey, CancelFlag>, key: TimerKey) -> bool {
+    flags.remove(key).is_some_and(|f| f.is_cancelled())
+}
+
+/// 取消全部已标记取消的任务，弹出和取消时已移除标记，剩余的标记都对应定时器中的任务
+fn purge<T, const N0: usize, const N: usize, const L: usize>(
+    timer: &mut Timer<T, N0, N, L>,
+    flags: &mut SecondaryMap<TimerKey, CancelFlag>,
+) {
+    flags.retain(|key, flag| {
+        if flag.is_cancelled() {
+            timer.cancel(key);
+            return false;
+        }
+        true
+    });
+}
+
+impl<T> Drop for TimerService<T> {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.handle.send(Command::Shutdown);
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn test_service() {
+        let clock = ManualClock::default();
+        let (s, r) = channel();
+        let service = TimerService::spawn_with_clock::<16, 16, 1, _, _>(
+            clock.clone(),
+            Duration::from_millis(1),
+            move |_, v| s.send(v).unwrap(),
+        );
+        let handle = service.handle();
+        handle.schedule(20, 2).unwrap();
+        let k = handle.schedule(10, 1).unwrap();
+        service.schedule(5, 0).unwrap();
+        let flag = handle.schedule_cancellable(15, 5).unwrap();
+        service
+            .handle()
+            .schedule_cancellable(100000, 6)
+            .unwrap()
+            .cancel();
+        assert!(handle.cancel(k));
+        flag.cancel();
+        // 等待服务返回键，保证之前的命令都已处理
+        service.schedule(100000, 3).unwrap();
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(r.recv_timeout(Duration::from_secs(5)), Ok(0));
+        assert!(r.try_recv().is_err());
+        clock.advance(Duration::from_millis(15));
+        assert_eq!(r.recv_timeout(Duration::from_secs(5)), Ok(2));
+        let pending = service.shutdown();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].2, 3);
+        assert!(r.try_recv().is_err());
+        assert!(handle.schedule(1, 4).is_none());
+    }
+
+    #[test]
+    fn test_service_never_early() {
+        let clock = ManualClock::default();
+        let (s, r) = channel();
+        let c = clock.clone();
+        let service = TimerService::spawn_with_clock::<16, 16, 1, _, _>(
+            clock.clone(),
+            Duration::from_millis(1),
+            move |_, (start, dur): (Duration, Duration)| s.send((c.now() - start, dur)).unwrap(),
+        );
+        // 在滚动周期内的不同位置放入，交付时经过的时钟时长不能少于请求的时长
+        for i in 0..40u64 {
+            let dur = Duration::from_micros(300 * (i % 7));
+            service.schedule_after(dur, (clock.now(), dur)).unwrap();
+            clock.advance(Duration::from_micros(230));
+        }
+        clock.advance(Duration::from_millis(5));
+        for _ in 0..40 {
+            let (elapsed, dur) = r.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert!(elapsed >= dur, "{:?} < {:?}", elapsed, dur);
+        }
+    }
+
+    #[test]
+    fn test_service_purge() {
+        let service = TimerService::spawn_with_clock::<16, 16, 1, _, _>(
+            ManualClock::default(),
+            Duration::from_millis(1),
+            |_, _| {},
+        );
+        let handle = service.handle();
+        let payload = Arc::new(());
+        let flags: Vec<_> = (0..64)
+            .map(|_| {
+                handle
+                    .schedule_cancellable(100000, payload.clone())
+                    .unwrap()
+            })
+            .collect();
+        for flag in &flags {
+            flag.cancel();
+        }
+        // 带取消标记的任务数量翻倍时清理，已标记取消的任务不等到期就被丢弃
+        for _ in 0..64 {
+            handle
+                .schedule_cancellable(100000, payload.clone())
+                .unwrap();
+        }
+        handle.schedule(100000, payload.clone()).unwrap();
+        assert_eq!(Arc::strong_count(&payload), 1 + 64 + 1);
+        assert_eq!(service.shutdown().len(), 64 + 1);
+        assert_eq!(Arc::strong_count(&payload), 1);
+    }
+
+    #[test]
+    fn test_service_sink_panic() {
+        let clock = ManualClock::default();
+        let (s, r) = channel();
+        let service = TimerService::spawn_with_clock::<16, 16, 1, _, _>(
+            clock.clone(),
+            Duration::from_millis(1),
+            move |_, v| {
+                assert_ne!(v, 1, "sink panic");
+                s.send(v).unwrap();
+            },
+        );
+        service.schedule(5, 1).unwrap();
+        service.schedule(10, 2).unwrap();
+        service.schedule(100000, 3).unwrap();
+        clock.advance(Duration::from_millis(10));
+        // sink的panic只丢弃当次任务，服务线程继续处理后续任务和关闭
+        assert_eq!(r.recv_timeout(Duration::from_secs(5)), Ok(2));
+        let pending = service.shutdown();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].2, 3);
+    }
+}
@@ -0,0 +1,185 @@
+//! 定时器集合，按标识管理多个时间单位不同的定时器，统一推进时间，合并弹出到期任务
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{tick::Tick, Timer};
+
+/// 集合中一个定时器的统计
+#[cfg(not(feature = "minimal"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemberStats {
+    /// 定时任务的数量
+    pub len: usize,
+    /// 放入的任务数量
+    pub add_count: u64,
+    /// 到期弹出的任务数量
+    pub expired_count: u64,
+    /// 取消的任务数量
+    pub cancelled_count: u64,
+    /// 滚动次数
+    pub roll_count: u64,
+}
+
+/// 定时器集合，时间以基础单位计，每个定时器滚动一次对应unit个基础单位
+pub struct TimerSet<I, T, const N0: usize, const N: usize, const L: usize> {
+    members: Vec<(I, u64, Timer<T, N0, N, L>)>,
+    now: u64,
+}
+
+impl<I, T, const N0: usize, const N: usize, const L: usize> Default for TimerSet<I, T, N0, N, L> {
+    fn default() -> Self {
+        TimerSet {
+            members: Vec::new(),
+            now: 0,
+        }
+    }
+}
+
+impl<I: PartialEq + Clone, T, const N0: usize, const N: usize, const L: usize>
+    TimerSet<I, T, N0, N, L>
+{
+    /// 添加一个定时器，unit为其滚动一次对应的基础时间单位数
+    /// * @return 标识已存在时返回false
+    pub fn add(&mut self, id: I, unit: u64) -> bool {
+        assert!(unit > 0, "timer unit must be positive");
+        if self.position(&id).is_some() {
+            return false;
+        }
+        self.members.push((id, unit, Default::default()));
+        true
+    }
+    /// 移除一个定时器
+    pub fn remove(&mut self, id: &I) -> Option<Timer<T, N0, N, L>> {
+        self.position(id).map(|i| self.members.remove(i).2)
+    }
+    /// 获得定时器
+    pub fn get(&self, id: &I) -> Option<&Timer<T, N0, N, L>> {
+        self.position(id).map(|i| &self.members[i].2)
+    }
+    /// 获得定时器
+    pub fn get_mut(&mut self, id: &I) -> Option<&mut Timer<T, N0, N, L>> {
+        self.position(id).map(move |i| &mut self.members[i].2)
+    }
+    /// 获得定时器的数量
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+    /// 是否没有定时器
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+    /// 获得全部定时器的定时任务数量之和
+    pub fn task_len(&self) -> usize {
+        self.members.iter().map(|m| m.2.len()).sum()
+    }
+    /// 获得当前时间，以基础单位计
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+    /// 向指定定时器放入一个定时任务，超时时间以该定时器的单位计
+    pub fn push(&mut self, id: &I, timeout: usize, el: T) -> Option<TimerKey> {
+        self.get_mut(id).map(|t| t.push(timeout, el))
+    }
+    /// 取消指定定时器上的定时任务
+    pub fn cancel(&mut self, id: &I, key: TimerKey) -> Option<T> {
+        self.get_mut(id).and_then(|t| t.cancel(key))
+    }
+    /// 推进时间，以基础单位计，时间不会倒退
+    pub fn advance(&mut self, now: u64) {
+        self.now = self.now.max(now);
+    }
+    /// 弹出当前时间内最早到期的一个定时任务，按到期时间乘以单位比较，到期时间相同时按添加定时器的顺序
+    /// * @tip 没有到期任务的定时器滚动到当前时间，之后放入的超时时间从当前时间算起
+    /// * @return `Option<(I, TimerKey, T)>` 来源定时器的标识、关键字和定时元素
+    pub fn pop(&mut self) -> Option<(I, TimerKey, T)> {
+        let now = self.now;
+        let mut min: Option<(u64, usize)> = None;
+        for (i, (_, unit, timer)) in self.members.iter_mut().enumerate() {
+            match timer
+                .next_expiry()
+                .map(Tick::get)
+                .filter(|d| *d <= now / *unit)
+            {
+                Some(deadline) => {
+                    let time = deadline.saturating_mul(*unit);
+                    if min.is_none_or(|m| time < m.0) {
+                        min = Some((time, i));
+                    }
+                }
+                None => {
+                    timer.is_ok(now / *unit);
+                }
+            }
+        }
+        let (_, i) = min?;
+        let (id, unit, timer) = &mut self.members[i];
+        timer
+            .pop_at(Tick(now / *unit))
+            .map(|(key, el)| (id.clone(), key, el))
+    }
+    /// 获得每个定时器的统计
+    #[cfg(not(feature = "minimal"))]
+    pub fn stats(&self) -> impl Iterator<Item = (&I, MemberStats)> {
+        self.members.iter().map(|(id, _, t)| {
+            (
+                id,
+                MemberStats {
+                    len: t.len(),
+                    add_count: t.add_count(),
+                    expired_count: t.expired_count(),
+                    cancelled_count: t.cancelled_count(),
+                    roll_count: t.now().get(),
+                },
+            )
+        })
+    }
+    fn position(&self, id: &I) -> Option<usize> {
+        self.members.iter().position(|m| &m.0 == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let mut set: TimerSet<&str, u32, 4, 4, 1> = Default::default();
+        assert!(set.add("net", 1));
+        assert!(set.add("game", 10));
+        assert!(!set.add("net", 2));
+        set.push(&"game", 3, 1).unwrap();
+        set.push(&"net", 25, 0).unwrap();
+        let k = set.push(&"net", 40, 2).unwrap();
+        set.push(&"net", 35, 3).unwrap();
+        assert_eq!(set.cancel(&"net", k), Some(2));
+        assert!(set.push(&"db", 1, 9).is_none());
+        set.advance(29);
+        assert_eq!(set.pop().map(|r| (r.0, r.2)), Some(("net", 0)));
+        assert!(set.pop().is_none());
+        set.advance(100);
+        assert_eq!(set.pop().map(|r| (r.0, r.2)), Some(("game", 1)));
+        assert_eq!(set.pop().map(|r| (r.0, r.2)), Some(("net", 3)));
+        assert!(set.pop().is_none());
+        #[cfg(not(feature = "minimal"))]
+        {
+            let stats: Vec<_> = set.stats().collect();
+            assert_eq!(stats[0].1.cancelled_count, 1);
+            assert_eq!(stats[1].1.expired_count, 1);
+            assert_eq!(stats[1].1.roll_count, 10);
+        }
+        // 按各定时器最早到期的任务排序，不受定时器已滚动到的位置影响
+        let mut set: TimerSet<&str, u32, 4, 4, 1> = Default::default();
+        set.add("ms", 1);
+        set.add("cs", 10);
+        set.push(&"cs", 1, 10).unwrap();
+        set.push(&"cs", 5, 50).unwrap();
+        set.push(&"ms", 30, 30).unwrap();
+        set.push(&"ms", 45, 45).unwrap();
+        // cs的任务在滚动时进入就绪队列，滚动次数已到6
+        set.get_mut(&"cs").unwrap().advance_to(Tick(6));
+        set.advance(100);
+        let order: Vec<_> = std::iter::from_fn(|| set.pop()).map(|r| r.2).collect();
+        assert_eq!(order, [10, 30, 45, 50]);
+    }
+}
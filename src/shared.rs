@@ -0,0 +1,203 @@
+//! 共享定时器，多个线程通过克隆的句柄对同一个定时器放入、取消和弹出
+//! 在`--cfg loom`下同步原语替换为loom的实现，用于验证跨线程的操作顺序
+
+#[cfg(loom)]
+pub(crate) use loom::sync::{Arc, Mutex, MutexGuard};
+#[cfg(not(loom))]
+pub(crate) use std::sync::{Arc, Mutex, MutexGuard};
+
+use pi_slot_wheel::TimerKey;
+use slotmap::SecondaryMap;
+
+use crate::{tick::Tick, Timer};
+
+/// 远程取消句柄，由驱动线程在弹出或 `purge` 时移除被标记的任务
+pub use crate::cancel::CancelFlag;
+
+/// 远程取消的状态
+struct Remote<T> {
+    /// 带远程取消句柄的任务及其取消标记
+    flags: SecondaryMap<TimerKey, CancelFlag>,
+    /// 移除远程取消的任务时调用
+    on_cancel: Option<Box<dyn FnMut(TimerKey, T) + Send>>,
+}
+
+impl<T> Default for Remote<T> {
+    fn default() -> Self {
+        Remote {
+            flags: Default::default(),
+            on_cancel: None,
+        }
+    }
+}
+
+/// 共享定时器的句柄，克隆后指向同一个定时器
+pub struct SharedTimer<T, const N0: usize, const N: usize, const L: usize> {
+    inner: Arc<Mutex<Timer<T, N0, N, L>>>,
+    // 先锁定时器再锁远程取消的状态
+    remote: Arc<Mutex<Remote<T>>>,
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Clone for SharedTimer<T, N0, N, L> {
+    fn clone(&self) -> Self {
+        SharedTimer {
+            inner: self.inner.clone(),
+            remote: self.remote.clone(),
+        }
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Default for SharedTimer<T, N0, N, L> {
+    fn default() -> Self {
+        SharedTimer {
+            inner: Arc::new(Mutex::new(Default::default())),
+            remote: Arc::new(Mutex::new(Default::default())),
+        }
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> SharedTimer<T, N0, N, L> {
+    /// 锁住定时器，用于批量操作
+    /// * @tip 持有锁的线程panic后，锁不会被毒化，定时器的状态依然可用
+    pub fn lock(&self) -> MutexGuard<'_, Timer<T, N0, N, L>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// 放入一个定时任务
+    pub fn push(&self, timeout: usize, el: T) -> TimerKey {
+        self.lock().push(timeout, el)
+    }
+    /// 放入一个定时任务，并返回可在任意线程使用的远程取消句柄
+    pub fn push_cancellable(&self, timeout: usize, el: T) -> (TimerKey, CancelFlag) {
+        let mut timer = self.lock();
+        let key = timer.push(timeout, el);
+        let handle = CancelFlag::default();
+        self.remote().flags.insert(key, handle.clone());
+        (key, handle)
+    }
+    /// 设置移除远程取消的任务时的回调函数，参数为关键字和定时元素
+    /// * @tip 回调在持有锁时调用，不能再操作本定时器
+    pub fn set_on_remote_cancel<F: FnMut(TimerKey, T) + Send + 'static>(&self, f: F) {
+        self.remote().on_cancel = Some(Box::new(f));
+    }
+    /// 取消定时任务
+    pub fn cancel(&self, key: TimerKey) -> Option<T> {
+        let mut timer = self.lock();
+        self.remote().flags.remove(key);
+        timer.cancel(key)
+    }
+    /// 弹出指定时间内的一个定时任务
+    pub fn pop(&self, now: u64) -> Option<T> {
+        self.pop_kv(now).map(|(_, el)| el)
+    }
+    /// 弹出指定时间内的一个定时任务及其键，跳过已远程取消的任务
+    pub fn pop_kv(&self, now: u64) -> Option<(TimerKey, T)> {
+        let mut timer = self.lock();
+        loop {
+            let (key, el) = timer.pop_at(Tick(now))?;
+            let mut remote = self.remote();
+            match remote.flags.remove(key) {
+                Some(handle) if handle.is_cancelled() => {
+                    if let Some(f) = &mut remote.on_cancel {
+                        f(key, el);
+                    }
+                }
+                _ => return Some((key, el)),
+            }
+        }
+    }
+    /// 移除全部已远程取消的任务，不等到期
+    /// * @return `usize` 移除的任务数量
+    pub fn purge(&self) -> usize {
+        let mut timer = self.lock();
+        let mut remote = self.remote();
+        let remote = &mut *remote;
+        let mut count = 0;
+        remote.flags.retain(|key, handle| {
+            if !handle.is_cancelled() {
+                return timer.slot.contains_key(key);
+            }
+            if let Some(el) = timer.cancel(key) {
+                count += 1;
+                if let Some(f) = &mut remote.on_cancel {
+                    f(key, el);
+                }
+            }
+            false
+        });
+        count
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+    fn remote(&self) -> MutexGuard<'_, Remote<T>> {
+        self.remote.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RUSTFLAGS="--cfg loom" cargo test --features shared --release test_loom
+    #[cfg(loom)]
+    #[test]
+    fn test_loom_shared() {
+        loom::model(|| {
+            let timer: SharedTimer<u32, 4, 4, 1> = Default::default();
+            let key = timer.push(0, 0);
+            let t1 = timer.clone();
+            let h1 = loom::thread::spawn(move || {
+                t1.push(0, 1);
+                t1.cancel(key)
+            });
+            let t2 = timer.clone();
+            let h2 = loom::thread::spawn(move || {
+                let mut r = Vec::new();
+                while let Some(v) = t2.pop(0) {
+                    r.push(v);
+                }
+                r
+            });
+            let cancelled = h1.join().unwrap();
+            let mut popped = h2.join().unwrap();
+            while let Some(v) = timer.pop(0) {
+                popped.push(v);
+            }
+            // 每个任务恰好被弹出或取消一次，且同一时间的任务先进先出
+            assert_eq!(cancelled.is_some(), !popped.contains(&0));
+            assert!(popped.contains(&1));
+            assert!(popped.windows(2).all(|w| w[0] < w[1]));
+            assert!(timer.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_cancel_handle() {
+        use std::sync::{Arc, Mutex};
+        let timer: SharedTimer<u32, 4, 4, 1> = Default::default();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = log.clone();
+        timer.set_on_remote_cancel(move |_, el| l.lock().unwrap().push(el));
+        let (_, h0) = timer.push_cancellable(1, 0);
+        let (_, h1) = timer.push_cancellable(2, 1);
+        let (_, h2) = timer.push_cancellable(100, 2);
+        timer.push(2, 3);
+        std::thread::spawn(move || {
+            h0.cancel();
+            h2.cancel();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(timer.purge(), 2);
+        assert_eq!(*log.lock().unwrap(), [0, 2]);
+        h1.cancel();
+        assert_eq!(timer.pop(2), Some(3));
+        assert_eq!(*log.lock().unwrap(), [0, 2, 1]);
+        assert!(timer.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! mio事件源适配，用timerfd在最近的定时任务到期时产生可读事件，网络循环无需额外的定时线程
+
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    time::Duration,
+};
+
+use mio::{event::Source, unix::SourceFd, Interest, Registry, Token};
+
+use crate::{tick::TickDuration, Timer};
+
+/// 定时器事件源
+pub struct TimerSource<T, const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<T, N0, N, L>,
+    fd: OwnedFd,
+    tick: Duration,
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> TimerSource<T, N0, N, L> {
+    /// 创建事件源，tick为定时器滚动一次对应的时长
+    pub fn new(tick: Duration) -> io::Result<Self> {
+        let fd = unsafe {
+            libc::timerfd_create(
+                libc::CLOCK_MONOTONIC,
+                libc::TFD_NONBLOCK | libc::TFD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(TimerSource {
+            timer: Default::default(),
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            tick,
+        })
+    }
+    /// 获得内部的定时器
+    pub fn timer(&self) -> &Timer<T, N0, N, L> {
+        &self.timer
+    }
+    /// 获得内部的定时器，修改后应调用 `arm` 重新设置到期事件
+    pub fn timer_mut(&mut self) -> &mut Timer<T, N0, N, L> {
+        &mut self.timer
+    }
+    /// 按最近的到期时间设置timerfd，没有定时任务时解除
+    pub fn arm(&mut self) -> io::Result<()> {
+        let value = match self.timer.next_expiry_in().map(TickDuration::get) {
+            // 值为0会解除timerfd，已到期时设为最小的1纳秒
+            Some(ticks) => (self.tick.as_nanos() as u64).saturating_mul(ticks).max(1),
+            None => 0,
+        };
+        let spec = libc::itimerspec {
+            it_interval: libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            it_value: libc::timespec {
+                tv_sec: (value / 1_000_000_000) as libc::time_t,
+                tv_nsec: (value % 1_000_000_000) as libc::c_long,
+            },
+        };
+        let r =
+            unsafe { libc::timerfd_settime(self.fd.as_raw_fd(), 0, &spec, std::ptr::null_mut()) };
+        if r < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    /// 收到可读事件后读取timerfd，清除就绪状态
+    /// * @return `bool` timerfd是否已触发
+    pub fn ack(&mut self) -> io::Result<bool> {
+        let mut buf = [0u8; 8];
+        let r = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                8,
+            )
+        };
+        if r < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::WouldBlock {
+                return Ok(false);
+            }
+            return Err(e);
+        }
+        Ok(true)
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> AsRawFd for TimerSource<T, N0, N, L> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Source for TimerSource<T, N0, N, L> {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.fd.as_raw_fd()).register(registry, token, interests)
+    }
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.fd.as_raw_fd()).reregister(registry, token, interests)
+    }
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.fd.as_raw_fd()).deregister(registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source() {
+        use mio::{Events, Interest, Poll, Token};
+        use std::time::Duration;
+        let mut poll = Poll::new().unwrap();
+        let mut source: TimerSource<u32, 8, 8, 1> =
+            TimerSource::new(Duration::from_millis(1)).unwrap();
+        poll.registry()
+            .register(&mut source, Token(0), Interest::READABLE)
+            .unwrap();
+        source.timer_mut().push(3, 1);
+        source.timer_mut().push(20, 2);
+        assert_eq!(
+            source.timer().next_expiry_in().map(TickDuration::get),
+            Some(3)
+        );
+        source.arm().unwrap();
+        let mut events = Events::with_capacity(4);
+        poll.poll(&mut events, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(events.iter().next().map(|e| e.token()), Some(Token(0)));
+        assert!(source.ack().unwrap());
+        assert_eq!(source.timer_mut().pop(3), Some(1));
+        assert_eq!(
+            source.timer().next_expiry_in().map(TickDuration::get),
+            Some(17)
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! 测试工具，提供定时器的操作序列和参考模型，用于基于模型的属性测试
+//! 以及基于手动时钟的 `TestTimer`，用声明式的断言代替手写的弹出循环
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    fmt::Debug,
+    time::Duration,
+};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::{
+    clock::{ClockedTimer, ManualClock},
+    tick::Tick,
+    Timer,
+};
+
+/// 定时器操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Op {
+    /// 放入一个定时任务
+    Push { timeout: u16 },
+    /// 按绝对时间放入一个定时任务
+    PushTime { time: u32 },
+    /// 取消第index个放入的定时任务，超出范围时取模
+    Cancel { index: u16 },
+    /// 滚动一次，经过的到期任务留待之后弹出
+    Roll,
+    /// 弹出当前时间加上advance之内的一个定时任务
+    Pop { advance: u8 },
+}
+
+/// 参考模型，用二叉堆按到期时间和放入顺序排列定时任务
+#[derive(Debug, Default)]
+pub struct Model {
+    now: u64,
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+    cancelled: HashSet<usize>,
+    len: usize,
+}
+
+impl Model {
+    /// 获得当前时间
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+    /// 获得定时任务的数量
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// 是否没有定时任务
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// 放入一个定时任务，id为放入顺序
+    pub fn push(&mut self, timeout: usize, id: usize) {
+        self.heap.push(Reverse((self.now + timeout as u64, id)));
+        self.len += 1;
+    }
+    /// 按绝对时间放入一个定时任务，时间已过的在当前时刻到期
+    pub fn push_time(&mut self, time: u64, id: usize) {
+        self.heap.push(Reverse((time.max(self.now), id)));
+        self.len += 1;
+    }
+    /// 取消定时任务
+    pub fn cancel(&mut self, id: usize) -> bool {
+        if self.cancelled.contains(&id) || !self.heap.iter().any(|it| it.0 .1 == id) {
+            return false;
+        }
+        self.cancelled.insert(id);
+        self.len -= 1;
+        true
+    }
+    /// 弹出指定时间内到期的一个定时任务
+    pub fn pop(&mut self, now: u64) -> Option<usize> {
+        while let Some(&Reverse((deadline, id))) = self.heap.peek() {
+            if self.cancelled.remove(&id) {
+                self.heap.pop();
+                continue;
+            }
+            if deadline > now {
+                break;
+            }
+            self.heap.pop();
+            self.len -= 1;
+            self.now = self.now.max(deadline);
+            return Some(id);
+        }
+        self.now = self.now.max(now);
+        None
+    }
+    /// 滚动一次
+    pub fn roll(&mut self) {
+        self.now += 1;
+    }
+}
+
+/// 在定时器和参考模型上执行同一操作序列，逐步比较结果
+/// * @return 第一个不一致的操作的描述
+pub fn check<const N0: usize, const N: usize, const L: usize>(
+    ops: &[Op],
+) -> Result<(), String> {
+    let mut timer: Timer<usize, N0, N, L> = Default::default();
+    let mut model = Model::default();
+    let mut keys: Vec<TimerKey> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        match *op {
+            Op::Push { timeout } => {
+                keys.push(timer.push(timeout as usize, keys.len()));
+                model.push(timeout as usize, keys.len() - 1);
+            }
+            Op::PushTime { time } => {
+                keys.push(timer.push_at(Tick(time as u64), keys.len()));
+                model.push_time(time as u64, keys.len() - 1);
+            }
+            Op::Cancel { index } => {
+                if keys.is_empty() {
+                    continue;
+                }
+                let id = index as usize % keys.len();
+                let r = timer.cancel(keys[id]);
+                if r.is_some() != model.cancel(id) || r.is_some_and(|el| el != id) {
+                    return Err(format!("op {}: {:?} cancel {:?}", i, op, r));
+                }
+            }
+            Op::Roll => {
+                timer.roll();
+                model.roll();
+            }
+            Op::Pop { advance } => {
+                let now = timer.now().get() + advance as u64;
+                let (a, b) = (timer.pop(now), model.pop(now));
+                if a != b {
+                    return Err(format!("op {}: {:?} pop {:?}, expect {:?}", i, op, a, b));
+                }
+            }
+        }
+        if timer.len() != model.len() || timer.now().get() != model.now() {
+            return Err(format!(
+                "op {}: {:?} len {} now {}, expect len {} now {}",
+                i,
+                op,
+                timer.len(),
+                timer.now().get(),
+                model.len(),
+                model.now()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 测试用定时器，由手动时钟驱动，时间以滚动次数表示
+pub struct TestTimer<T, const N0: usize, const N: usize, const L: usize> {
+    timer: ClockedTimer<T, ManualClock, N0, N, L>,
+    clock: ManualClock,
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> Default for TestTimer<T, N0, N, L> {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(1))
+    }
+}
+
+impl<T, const N0: usize, const N: usize, const L: usize> TestTimer<T, N0, N, L> {
+    /// 创建定时器，滚动一次对应tick时长
+    pub fn new(tick: Duration) -> Self {
+        let clock = ManualClock::default();
+        TestTimer {
+            timer: ClockedTimer::new(clock.clone(), tick),
+            clock,
+        }
+    }
+    /// 获得带时钟的定时器
+    pub fn timer(&self) -> &ClockedTimer<T, ManualClock, N0, N, L> {
+        &self.timer
+    }
+    /// 获得带时钟的定时器
+    pub fn timer_mut(&mut self) -> &mut ClockedTimer<T, ManualClock, N0, N, L> {
+        &mut self.timer
+    }
+    /// 获得手动时钟
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+    /// 获得当前时间，即从起点经过的滚动次数
+    pub fn now(&self) -> u64 {
+        self.timer.now()
+    }
+    /// 放入一个经过timeout次滚动后到期的定时任务
+    pub fn push(&mut self, timeout: usize, el: T) -> TimerKey {
+        let deadline = self.now() + timeout as u64;
+        self.timer.timer_mut().push_at(Tick(deadline), el)
+    }
+    /// 放入一个经过指定时长后到期的定时任务，到期时间向上取整
+    pub fn push_after(&mut self, after: Duration, el: T) -> TimerKey {
+        self.timer.push(after, el)
+    }
+    /// 取消定时任务
+    pub fn cancel(&mut self, key: TimerKey) -> Option<T> {
+        self.timer.cancel(key)
+    }
+    /// 推进ticks次滚动的时长，按到期顺序收集当前已到期和期间到期的全部定时任务
+    pub fn advance_and_collect(&mut self, ticks: u64) -> Vec<(TimerKey, T)> {
+        let mut r = Vec::new();
+        for i in 0..=ticks {
+            if i > 0 {
+                self.clock.advance(self.timer.tick());
+            }
+            while let Some(it) = self.timer.pop() {
+                r.push(it);
+            }
+        }
+        r
+    }
+    /// 推进到deadline，断言到达之前没有满足matcher的定时任务到期，且在deadline有满足matcher的定时任务到期
+    /// * @tip deadline等于当前时间时只检查当前已到期的任务
+    /// * @return 推进期间到期的全部定时任务
+    #[track_caller]
+    pub fn expect_fires_at<F: Fn(&T) -> bool>(
+        &mut self,
+        deadline: u64,
+        matcher: F,
+    ) -> Vec<(TimerKey, T)> {
+        let now = self.now();
+        assert!(
+            deadline >= now,
+            "deadline {} is before now {}",
+            deadline,
+            now
+        );
+        let mut r = self.advance_and_collect(0);
+        if deadline > now {
+            r.extend(self.advance_and_collect(deadline - now - 1));
+            if r.iter().any(|(_, el)| matcher(el)) {
+                panic!("fired at or before {}, expect {}", self.now(), deadline);
+            }
+            r.extend(self.advance_and_collect(1));
+        }
+        assert!(
+            r.iter().any(|(_, el)| matcher(el)),
+            "nothing matched fired at {}",
+            deadline
+        );
+        r
+    }
+}
+
+impl<T: Debug, const N0: usize, const N: usize, const L: usize> TestTimer<T, N0, N, L> {
+    /// 断言没有定时任务，失败时列出剩余的定时任务
+    #[track_caller]
+    pub fn assert_empty(&self) {
+        let timer = self.timer.timer();
+        assert!(
+            timer.is_empty(),
+            "{} pending:\n{}",
+            timer.len(),
+            timer.report(16)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::{RngCore, SeedableRng};
+
+    use crate::{Overflow, Rounding};
+
+    #[test]
+    fn test_model() {
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(66666);
+        for _ in 0..200 {
+            let ops: Vec<_> = (0..300)
+                .map(|_| match rng.next_u32() % 5 {
+                    0 => Op::Push {
+                        timeout: (rng.next_u32() % 80) as u16,
+                    },
+                    4 => Op::PushTime {
+                        time: rng.next_u32() % 1000,
+                    },
+                    1 => Op::Cancel {
+                        index: rng.next_u32() as u16,
+                    },
+                    2 => Op::Roll,
+                    _ => Op::Pop {
+                        advance: (rng.next_u32() % 8) as u8,
+                    },
+                })
+                .collect();
+            check::<4, 4, 2>(&ops).unwrap();
+            check::<8, 2, 1>(&ops).unwrap();
+            // 槽数不是2的幂
+            check::<100, 10, 1>(&ops).unwrap();
+            check::<6, 3, 2>(&ops).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_never_early() {
+        let mut rng = pcg_rand::Pcg32::seed_from_u64(203);
+        for lazy in [false, true] {
+            // 小轮使长定时任务经过内联溢出和堆的级联
+            let mut timer: Timer<u64, 4, 4, 1> = Default::default();
+            timer.set_never_early(true);
+            timer.set_coalesce(3);
+            timer.set_rounding(Rounding::Nearest);
+            timer.set_overflow(Overflow::Clamp);
+            timer.set_lazy_restamp(lazy);
+            let mut now = 0;
+            for _ in 0..20000 {
+                match rng.next_u32() % 4 {
+                    0 => {
+                        let timeout = (rng.next_u32() % 100) as usize;
+                        timer.push_with_rounding(timeout, now + timeout as u64, Rounding::Floor);
+                    }
+                    1 => {
+                        // 时间已过的任务在当前时刻到期，不算提前
+                        let time = (now + (rng.next_u32() % 100) as u64).saturating_sub(10);
+                        timer.push_at(Tick(time), time.max(now));
+                    }
+                    _ => {
+                        now += (rng.next_u32() % 3) as u64;
+                        while let Some(deadline) = timer.pop(now) {
+                            assert!(deadline <= timer.now().get(), "{} {}", deadline, now);
+                        }
+                    }
+                }
+            }
+            while !timer.is_empty() {
+                now += 1;
+                while let Some(deadline) = timer.pop(now) {
+                    assert!(deadline <= timer.now().get());
+                }
+            }
+        }
+        // 不足一次滚动的时长向上取整
+        let mut timer: TestTimer<u32, 8, 8, 1> = Default::default();
+        timer.push_after(Duration::from_micros(1500), 1);
+        timer.expect_fires_at(2, |el| *el == 1);
+    }
+
+    #[test]
+    fn test_test_timer() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        let mut timer: TestTimer<&str, 8, 8, 1> = Default::default();
+        timer.push(3, "a");
+        timer.push(3, "b");
+        timer.push(200, "c");
+        let fired = timer.expect_fires_at(3, |el| *el == "b");
+        assert_eq!(fired.len(), 2);
+        assert_eq!(timer.now(), 3);
+        assert!(timer.advance_and_collect(100).is_empty());
+        // 到期时间不符时断言失败
+        let r = catch_unwind(AssertUnwindSafe(|| timer.expect_fires_at(150, |el| *el == "c")));
+        assert!(r.is_err());
+        let r = catch_unwind(AssertUnwindSafe(|| timer.assert_empty()));
+        assert!(r.is_err());
+        let fired = timer.advance_and_collect(100);
+        assert_eq!(fired.iter().map(|(_, el)| *el).collect::<Vec<_>>(), ["c"]);
+        timer.assert_empty();
+    }
+}
@@ -0,0 +1,262 @@
+//! 时间的强类型表示，避免混用表示时刻的u64和表示时长的usize
+//! `Epoch` 把时刻0绑定到系统时间和单调时间，用于把到期时刻显示为人能读懂的时间
+
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    time::{Duration, Instant, SystemTime},
+};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::Timer;
+
+/// 时刻，以滚动次数计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tick(pub u64);
+
+/// 时长，以滚动次数计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TickDuration(pub u64);
+
+impl Tick {
+    /// 时刻0
+    pub const ZERO: Tick = Tick(0);
+    /// 获得原始值
+    pub fn get(self) -> u64 {
+        self.0
+    }
+    /// 到另一个时刻经过的时长，另一个时刻更晚时为0
+    pub fn saturating_since(self, earlier: Tick) -> TickDuration {
+        TickDuration(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl TickDuration {
+    /// 时长0
+    pub const ZERO: TickDuration = TickDuration(0);
+    /// 获得原始值
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for Tick {
+    fn from(v: u64) -> Self {
+        Tick(v)
+    }
+}
+impl From<Tick> for u64 {
+    fn from(v: Tick) -> Self {
+        v.0
+    }
+}
+impl From<u64> for TickDuration {
+    fn from(v: u64) -> Self {
+        TickDuration(v)
+    }
+}
+impl From<TickDuration> for u64 {
+    fn from(v: TickDuration) -> Self {
+        v.0
+    }
+}
+
+impl fmt::Display for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tick {}", self.0)
+    }
+}
+impl fmt::Display for TickDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ticks", self.0)
+    }
+}
+
+impl Add<TickDuration> for Tick {
+    type Output = Tick;
+    fn add(self, rhs: TickDuration) -> Tick {
+        Tick(self.0 + rhs.0)
+    }
+}
+impl AddAssign<TickDuration> for Tick {
+    fn add_assign(&mut self, rhs: TickDuration) {
+        self.0 += rhs.0;
+    }
+}
+impl Sub<TickDuration> for Tick {
+    type Output = Tick;
+    fn sub(self, rhs: TickDuration) -> Tick {
+        Tick(self.0 - rhs.0)
+    }
+}
+impl SubAssign<TickDuration> for Tick {
+    fn sub_assign(&mut self, rhs: TickDuration) {
+        self.0 -= rhs.0;
+    }
+}
+impl Sub for Tick {
+    type Output = TickDuration;
+    fn sub(self, rhs: Tick) -> TickDuration {
+        TickDuration(self.0 - rhs.0)
+    }
+}
+impl Add for TickDuration {
+    type Output = TickDuration;
+    fn add(self, rhs: TickDuration) -> TickDuration {
+        TickDuration(self.0 + rhs.0)
+    }
+}
+impl AddAssign for TickDuration {
+    fn add_assign(&mut self, rhs: TickDuration) {
+        self.0 += rhs.0;
+    }
+}
+impl Sub for TickDuration {
+    type Output = TickDuration;
+    fn sub(self, rhs: TickDuration) -> TickDuration {
+        TickDuration(self.0 - rhs.0)
+    }
+}
+impl SubAssign for TickDuration {
+    fn sub_assign(&mut self, rhs: TickDuration) {
+        self.0 -= rhs.0;
+    }
+}
+
+/// 使用强类型时间的接口，与使用整数的接口等价，整数的接口已弃用，只为兼容而保留
+#[allow(deprecated)]
+impl<T, const N0: usize, const N: usize, const L: usize> Timer<T, N0, N, L> {
+    /// 获得当前时刻
+    pub fn now(&self) -> Tick {
+        Tick(self.roll_count())
+    }
+    /// 放入一个经过指定时长后到期的定时任务
+    pub fn push_after(&mut self, after: TickDuration, el: T) -> TimerKey {
+        self.push_at(self.now() + after, el)
+    }
+    /// 放入一个在指定时刻到期的定时任务，时刻已过的在当前时刻到期
+    pub fn push_at(&mut self, at: Tick, el: T) -> TimerKey {
+        self.push_time(at.0, el)
+    }
+    /// 弹出指定时刻内的一个关键字和定时任务
+    pub fn pop_at(&mut self, now: Tick) -> Option<(TimerKey, T)> {
+        self.pop_kv(now.0)
+    }
+    /// 滚动到指定时刻，到期任务移入就绪队列
+    pub fn advance_to(&mut self, now: Tick) {
+        self.advance(now.0)
+    }
+    /// 获得最近的到期时刻
+    pub fn next_expiry(&self) -> Option<Tick> {
+        self.earliest().map(Tick)
+    }
+    /// 获得距最近的到期时刻的时长，已到期时为0
+    pub fn next_expiry_in(&self) -> Option<TickDuration> {
+        self.next_timeout().map(TickDuration)
+    }
+}
+
+/// 时间起点，把时刻0绑定到系统时间和单调时间，每个时刻对应tick时长
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Epoch {
+    system: SystemTime,
+    instant: Instant,
+    tick: Duration,
+}
+
+impl Epoch {
+    /// 以当前时间为时刻0
+    pub fn now(tick: Duration) -> Self {
+        Epoch::new(SystemTime::now(), Instant::now(), tick)
+    }
+    /// 以指定的系统时间和单调时间为时刻0，两者应为同一时间
+    pub fn new(system: SystemTime, instant: Instant, tick: Duration) -> Self {
+        assert!(!tick.is_zero(), "tick must be positive");
+        Epoch {
+            system,
+            instant,
+            tick,
+        }
+    }
+    /// 获得时刻0的系统时间
+    pub fn system(&self) -> SystemTime {
+        self.system
+    }
+    /// 获得时刻0的单调时间
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+    /// 获得每个时刻对应的时长
+    pub fn tick(&self) -> Duration {
+        self.tick
+    }
+    /// 获得时刻对应的系统时间
+    pub fn tick_to_systemtime(&self, tick: Tick) -> SystemTime {
+        self.system + self.duration(tick)
+    }
+    /// 获得系统时间所在的时刻，向下取整，早于时刻0的为时刻0
+    pub fn systemtime_to_tick(&self, time: SystemTime) -> Tick {
+        self.ticks(time.duration_since(self.system).unwrap_or_default())
+    }
+    /// 获得时刻对应的单调时间
+    pub fn tick_to_instant(&self, tick: Tick) -> Instant {
+        self.instant + self.duration(tick)
+    }
+    /// 获得单调时间所在的时刻，向下取整，早于时刻0的为时刻0
+    pub fn instant_to_tick(&self, instant: Instant) -> Tick {
+        self.ticks(instant.saturating_duration_since(self.instant))
+    }
+    fn duration(&self, tick: Tick) -> Duration {
+        Duration::from_nanos((self.tick.as_nanos() as u64).saturating_mul(tick.0))
+    }
+    fn ticks(&self, dur: Duration) -> Tick {
+        Tick((dur.as_nanos() / self.tick.as_nanos()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch() {
+        use std::time::{Instant, UNIX_EPOCH};
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let instant = Instant::now();
+        let epoch = Epoch::new(start, instant, Duration::from_millis(10));
+        let at = epoch.tick_to_systemtime(Tick(250));
+        assert_eq!(at, start + Duration::from_millis(2500));
+        assert_eq!(
+            epoch.systemtime_to_tick(at + Duration::from_millis(9)),
+            Tick(250)
+        );
+        assert_eq!(epoch.systemtime_to_tick(UNIX_EPOCH), Tick::ZERO);
+        let at = epoch.tick_to_instant(Tick(3));
+        assert_eq!(at - instant, Duration::from_millis(30));
+        assert_eq!(epoch.instant_to_tick(at), Tick(3));
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.push(7, 0);
+        let deadline = timer.next_expiry().unwrap();
+        assert_eq!(
+            epoch.tick_to_systemtime(deadline),
+            start + Duration::from_millis(70)
+        );
+    }
+
+    #[test]
+    fn test_tick() {
+        let mut timer: Timer<u32, 4, 4, 1> = Default::default();
+        timer.push_after(TickDuration(5), 0);
+        timer.push_at(Tick(3), 1);
+        assert_eq!(timer.next_expiry(), Some(Tick(3)));
+        assert_eq!(timer.pop_at(Tick(2)), None);
+        assert_eq!(timer.now(), Tick(2));
+        assert_eq!(timer.next_expiry_in(), Some(TickDuration(1)));
+        timer.advance_to(timer.now() + TickDuration(10));
+        assert_eq!(timer.now() - Tick(2), TickDuration(10));
+        assert_eq!(timer.pop_at(Tick(12)).map(|r| r.1), Some(1));
+        assert_eq!(timer.pop_at(Tick(12)).map(|r| r.1), Some(0));
+        assert_eq!(Tick(1).saturating_since(Tick(5)), TickDuration::ZERO);
+    }
+}
@@ -0,0 +1,142 @@
+//! 看门狗，按名称注册带超时时间的看门狗，喂狗时原地重新定时，超时未喂的看门狗在检查时报告并可触发升级回调
+
+use std::{collections::HashMap, hash::Hash};
+
+use pi_slot_wheel::TimerKey;
+
+use crate::Timer;
+
+/// 饿死时调用的升级回调，参数为看门狗的名称
+pub type Escalate<K> = Box<dyn FnMut(&K) + Send>;
+
+struct Dog<K> {
+    /// 定时任务的关键字，饿死后为None，直到再次喂狗
+    key: Option<TimerKey>,
+    timeout: usize,
+    escalate: Option<Escalate<K>>,
+}
+
+/// 看门狗集合
+pub struct Watchdog<K, const N0: usize, const N: usize, const L: usize> {
+    timer: Timer<K, N0, N, L>,
+    dogs: HashMap<K, Dog<K>>,
+}
+
+impl<K, const N0: usize, const N: usize, const L: usize> Default for Watchdog<K, N0, N, L> {
+    fn default() -> Self {
+        Watchdog {
+            timer: Default::default(),
+            dogs: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, const N0: usize, const N: usize, const L: usize> Watchdog<K, N0, N, L> {
+    /// 获得内部的定时器，定时元素为看门狗的名称
+    pub fn timer(&self) -> &Timer<K, N0, N, L> {
+        &self.timer
+    }
+    /// 注册看门狗，超时时间内未喂狗则饿死，已注册时替换原有的看门狗
+    pub fn register(&mut self, name: K, timeout: usize) {
+        self.insert(name, timeout, None);
+    }
+    /// 注册带升级回调的看门狗，饿死时调用回调
+    pub fn register_with<F: FnMut(&K) + Send + 'static>(&mut self, name: K, timeout: usize, f: F) {
+        self.insert(name, timeout, Some(Box::new(f)));
+    }
+    /// 注销看门狗
+    /// * @return `bool` 看门狗是否存在
+    pub fn unregister(&mut self, name: &K) -> bool {
+        match self.dogs.remove(name) {
+            Some(dog) => {
+                if let Some(key) = dog.key {
+                    self.timer.cancel(key);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+    /// 喂狗，按注册的超时时间重新定时，已饿死的看门狗重新开始计时
+    /// * @return `bool` 看门狗是否存在
+    pub fn feed(&mut self, name: &K) -> bool {
+        let dog = match self.dogs.get_mut(name) {
+            Some(dog) => dog,
+            None => return false,
+        };
+        match dog.key {
+            Some(key) => {
+                self.timer.reschedule(key, dog.timeout);
+            }
+            None => dog.key = Some(self.timer.push(dog.timeout, name.clone())),
+        }
+        true
+    }
+    /// 看门狗是否已饿死
+    pub fn is_starved(&self, name: &K) -> bool {
+        self.dogs.get(name).is_some_and(|dog| dog.key.is_none())
+    }
+    /// 检查指定时间内饿死的看门狗，调用其升级回调
+    /// * @return `Vec<K>` 本次饿死的看门狗名称，按到期顺序排列
+    pub fn expired(&mut self, now: u64) -> Vec<K> {
+        let mut starved = Vec::new();
+        while let Some(name) = self.timer.pop(now) {
+            if let Some(dog) = self.dogs.get_mut(&name) {
+                dog.key = None;
+                if let Some(f) = &mut dog.escalate {
+                    f(&name);
+                }
+            }
+            starved.push(name);
+        }
+        starved
+    }
+    /// 获得注册的看门狗数量
+    pub fn len(&self) -> usize {
+        self.dogs.len()
+    }
+    /// 是否没有注册的看门狗
+    pub fn is_empty(&self) -> bool {
+        self.dogs.is_empty()
+    }
+    fn insert(&mut self, name: K, timeout: usize, escalate: Option<Escalate<K>>) {
+        self.unregister(&name);
+        let key = self.timer.push(timeout, name.clone());
+        self.dogs.insert(
+            name,
+            Dog {
+                key: Some(key),
+                timeout,
+                escalate,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog() {
+        use std::sync::{Arc, Mutex};
+        let mut dogs: Watchdog<&str, 8, 8, 1> = Default::default();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = log.clone();
+        dogs.register_with("db", 3, move |name| l.lock().unwrap().push(*name));
+        dogs.register("net", 4);
+        assert!(!dogs.feed(&"disk"));
+        assert!(dogs.expired(2).is_empty());
+        assert!(dogs.feed(&"db"));
+        assert_eq!(dogs.expired(4), ["net"]);
+        assert!(dogs.is_starved(&"net"));
+        assert!(!dogs.is_starved(&"db"));
+        assert_eq!(dogs.expired(10), ["db"]);
+        assert_eq!(*log.lock().unwrap(), ["db"]);
+        assert!(dogs.feed(&"net"));
+        assert!(!dogs.is_starved(&"net"));
+        assert!(dogs.unregister(&"net"));
+        assert_eq!(dogs.expired(100), Vec::<&str>::new());
+        assert_eq!(dogs.len(), 1);
+    }
+}